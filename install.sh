@@ -4,9 +4,8 @@ echo "checking dependencies..."
 
 if ! command -v exiftool &> /dev/null
 then
-    echo "exiftool is not installed. you can download it at https://exiftool.org/install.html"
-    echo "exiting...."
-    exit 1
+    echo "exiftool is not installed, the built-in metadata reader will be used instead."
+    echo "you can download exiftool at https://exiftool.org/install.html"
 fi
 
 if ! command -v cargo &> /dev/null
@@ -1,11 +1,20 @@
 mod exiftool;
+mod native;
+mod tiff;
+mod cache;
+mod sidecar;
+mod queue;
+pub mod config;
 pub mod image;
+pub mod query;
 pub mod data;
 
 use std::collections::HashMap;
 
 use crate::data::image::Image;
-use crate::data::exiftool::Exiftool;
+use crate::data::cache::Cache;
+use crate::data::config::Config;
+use crate::data::queue::WriteQueue;
 use crate::data::image::Index;
 
 /////////////////////////
@@ -18,9 +27,38 @@ pub struct Folder
     pub images: Vec<Image>,
 }
 
+// the tool that reads and writes metadata, exiftool or the native rust implementation
+pub trait Backend: Send
+{
+    fn get_folder_data(&mut self, path: &String) -> Result<String, String>;
+    fn get_files_data(&mut self, paths: &Vec<String>) -> Result<String, String>;
+    fn set_notes(&mut self, path: &String, notes: &String) -> Result<String, String>;
+    fn set_tags(&mut self, path: &String, tag: &String) -> Result<String, String>;
+    fn set_link(&mut self, path: &String, tag: &String) -> Result<String, String>;
+    fn set_artist(&mut self, path: &String, tag: &String) -> Result<String, String>;
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum Property
+{
+    Artists,
+    Links,
+    Tags,
+    Notes,
+}
+
 pub struct Data 
 {
-    exif: Exiftool, 
+    queue: WriteQueue, 
+    writes_pending: usize,
+    write_failures: Vec<String>,
+    cache: Cache,
+    pub config: Config,
     pub folders: Vec<Folder>,
     pub taglist: HashMap::<String, Vec<Index>>,
+    pub artistlist: HashMap::<String, Vec<Index>>,
+    pub domainlist: HashMap::<String, Vec<Index>>,
+    pub noteslist: HashMap::<String, Vec<Index>>,
 }
@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use serde_json::{Value, Map, json};
+
+/////////////////////////
+
+const CACHE_VERSION: u64 = 3;
+
+// keeps the raw exiftool output per file, so a file only gets queried again
+// once its modification time or size changes.
+pub struct Cache
+{
+    path: Option<PathBuf>,
+    entries: HashMap<String, Entry>,
+    changed: bool,
+}
+
+struct Entry
+{
+    mtime: u64,
+    size: u64,
+    info: Value,
+}
+
+/////////////////////////
+
+pub fn cache_dir() -> Option<PathBuf>
+{
+    #[cfg(windows)]
+    let base = std::env::var_os("LOCALAPPDATA").map(PathBuf::from);
+
+    #[cfg(unix)]
+    let base = match std::env::var_os("XDG_CACHE_HOME")
+    {
+        Some(x) if x.len() > 0 => Some(PathBuf::from(x)),
+        _ => std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".cache")),
+    };
+
+    return base.map(|x| x.join("ref_viewer"));
+}
+
+pub fn file_stamp(path: &Path) -> Option<(u64, u64)>
+{
+    let meta = std::fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64;
+    return Some((mtime, meta.len()));
+}
+
+impl Cache
+{
+    // a cache that is never saved
+    pub fn new() -> Cache
+    {
+        return Cache{path: None, entries: HashMap::new(), changed: false};
+    }
+
+    pub fn load() -> Cache
+    {
+        let path = cache_dir().map(|x| x.join("metadata.json"));
+        let mut cache = Cache{path: path.clone(), ..Cache::new()};
+
+        let Some(path) = path else { return cache };
+        let Ok(text) = std::fs::read_to_string(&path) else { return cache };
+        let Ok(json) = serde_json::from_str::<Value>(&text) else { return cache };
+
+        if json.get("version").and_then(|x| x.as_u64()) != Some(CACHE_VERSION) {return cache;}
+        let Some(files) = json.get("files").and_then(|x| x.as_object()) else { return cache };
+
+        for (file, value) in files
+        {
+            let mtime = value.get("mtime").and_then(|x| x.as_u64());
+            let size = value.get("size").and_then(|x| x.as_u64());
+            let info = value.get("info");
+            if mtime.is_none() || size.is_none() || info.is_none() {continue;}
+
+            cache.entries.insert(file.clone(), Entry{
+                mtime: mtime.unwrap(),
+                size: size.unwrap(),
+                info: info.unwrap().clone()});
+        }
+
+        return cache;
+    }
+
+    pub fn save(&mut self) -> ()
+    {
+        if !self.changed {return;}
+        let Some(path) = &self.path else { return };
+
+        let mut files = Map::new();
+        for (file, entry) in &self.entries
+        {
+            files.insert(file.clone(), json!({
+                "mtime": entry.mtime,
+                "size": entry.size,
+                "info": entry.info}));
+        }
+
+        let json = json!({"version": CACHE_VERSION, "files": files});
+        if let Some(parent) = path.parent() {let _ = std::fs::create_dir_all(parent);}
+
+        // write to a temporary file first so a crash can't leave half a cache behind
+        let tmp = path.with_extension("json.tmp");
+        match std::fs::write(&tmp, json.to_string())
+        {
+            Ok(_) => {let _ = std::fs::rename(&tmp, path);},
+            Err(x) => println!("could not write metadata cache: {}", x),
+        }
+
+        self.changed = false;
+    }
+
+    pub fn get(&self, file: &String, mtime: u64, size: u64) -> Option<&Value>
+    {
+        match self.entries.get(file)
+        {
+            Some(x) if x.mtime == mtime && x.size == size => return Some(&x.info),
+            _ => return None,
+        }
+    }
+
+    pub fn insert(&mut self, file: String, mtime: u64, size: u64, info: Value) -> ()
+    {
+        self.entries.insert(file, Entry{mtime: mtime, size: size, info: info});
+        self.changed = true;
+    }
+
+    // drop entries of files that were in this folder but are no longer on disk
+    pub fn prune_folder(&mut self, folder: &Path, existing: &HashSet<String>) -> ()
+    {
+        let before = self.entries.len();
+        self.entries.retain(|file, _| {
+            Path::new(file).parent() != Some(folder) || existing.contains(file)
+        });
+
+        if self.entries.len() != before {self.changed = true;}
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use serde_json::{Value, Map, json};
+
+const MAX_SEARCH_HISTORY: usize = 50;
+
+/////////////////////////
+
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Copy)]
+pub enum Sidecar
+{
+    Off,
+    Json,
+    Xmp,
+}
+
+pub struct Config
+{
+    path: Option<PathBuf>,
+    pub sidecar: Sidecar,
+    pub sidecar_folders: HashMap<String, Sidecar>,
+
+    // tag rules: alias -> tag it stands for, tag -> tags it implies
+    pub aliases: HashMap<String, String>,
+    pub implications: HashMap<String, Vec<String>>,
+    pub write_implied: bool,
+
+    // namespace -> color, tags like "pose:sitting" are grouped under their namespace
+    pub namespaces: HashMap<String, [u8; 3]>,
+
+    // name and search text, in the order they were saved
+    pub saved_searches: Vec<(String, String)>,
+
+    // most recent first
+    pub search_history: Vec<String>,
+}
+
+/////////////////////////
+
+pub fn config_dir() -> Option<PathBuf>
+{
+    #[cfg(windows)]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+
+    #[cfg(unix)]
+    let base = match std::env::var_os("XDG_CONFIG_HOME")
+    {
+        Some(x) if x.len() > 0 => Some(PathBuf::from(x)),
+        _ => std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")),
+    };
+
+    return base.map(|x| x.join("ref_viewer"));
+}
+
+// "/a/b/" and "/a/b" should be the same folder
+fn folder_key(path: &Path) -> String
+{
+    return path.components().collect::<PathBuf>().to_string_lossy().into_owned();
+}
+
+fn sidecar_from_str(value: &str) -> Sidecar
+{
+    match value
+    {
+        "json" => Sidecar::Json,
+        "xmp" => Sidecar::Xmp,
+        _ => Sidecar::Off,
+    }
+}
+
+fn sidecar_to_str(value: Sidecar) -> &'static str
+{
+    match value
+    {
+        Sidecar::Off => "off",
+        Sidecar::Json => "json",
+        Sidecar::Xmp => "xmp",
+    }
+}
+
+// colors are stored as "#rrggbb"
+fn color_from_str(value: &str) -> Option<[u8; 3]>
+{
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {return None;}
+
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i+2)?, 16).ok();
+    return Some([channel(0)?, channel(2)?, channel(4)?]);
+}
+
+fn color_to_str(value: [u8; 3]) -> String
+{
+    return format!("#{:02x}{:02x}{:02x}", value[0], value[1], value[2]);
+}
+
+impl Config
+{
+    // the defaults, without a file to save to
+    pub fn new() -> Config
+    {
+        return Config{
+            path: None,
+            sidecar: Sidecar::Off,
+            sidecar_folders: HashMap::new(),
+            aliases: HashMap::new(),
+            implications: HashMap::new(),
+            write_implied: false,
+            namespaces: HashMap::new(),
+            saved_searches: Vec::new(),
+            search_history: Vec::new(),
+        };
+    }
+
+    pub fn load() -> Config
+    {
+        let path = config_dir().map(|x| x.join("config.json"));
+        let mut config = Config{path: path.clone(), ..Config::new()};
+
+        let Some(path) = path else { return config };
+        let Ok(text) = std::fs::read_to_string(&path) else { return config };
+        let json = match serde_json::from_str::<Value>(&text)
+        {
+            Ok(x) => x,
+            Err(x) =>
+            {
+                println!("could not read {}: {}", path.to_string_lossy(), x);
+                return config;
+            }
+        };
+
+        config.sidecar = sidecar_from_str(json.get("sidecar").and_then(|x| x.as_str()).unwrap_or(""));
+        if let Some(folders) = json.get("sidecar_folders").and_then(|x| x.as_object())
+        {
+            for (folder, value) in folders
+            {
+                config.sidecar_folders.insert(folder.clone(), sidecar_from_str(value.as_str().unwrap_or("")));
+            }
+        }
+
+        if let Some(aliases) = json.get("aliases").and_then(|x| x.as_object())
+        {
+            for (alias, tag) in aliases
+            {
+                let Some(tag) = tag.as_str() else { continue };
+                config.aliases.insert(alias.to_lowercase(), tag.to_lowercase());
+            }
+        }
+
+        if let Some(implications) = json.get("implications").and_then(|x| x.as_object())
+        {
+            for (tag, implied) in implications
+            {
+                let Some(implied) = implied.as_array() else { continue };
+                let implied = implied.iter().filter_map(|x| x.as_str()).map(|x| x.to_lowercase()).collect();
+                config.implications.insert(tag.to_lowercase(), implied);
+            }
+        }
+
+        config.write_implied = json.get("write_implied").and_then(|x| x.as_bool()).unwrap_or(false);
+
+        if let Some(namespaces) = json.get("namespaces").and_then(|x| x.as_object())
+        {
+            for (name, color) in namespaces
+            {
+                let color = color.as_str().and_then(color_from_str).unwrap_or([255, 255, 255]);
+                config.namespaces.insert(name.to_lowercase(), color);
+            }
+        }
+        for saved in json.get("saved_searches").and_then(|x| x.as_array()).into_iter().flatten()
+        {
+            let name = saved.get("name").and_then(|x| x.as_str());
+            let search = saved.get("search").and_then(|x| x.as_str());
+            if let (Some(name), Some(search)) = (name, search)
+            {
+                config.saved_searches.push((name.to_string(), search.to_string()));
+            }
+        }
+
+        for search in json.get("search_history").and_then(|x| x.as_array()).into_iter().flatten()
+        {
+            if let Some(x) = search.as_str() {config.search_history.push(x.to_string());}
+        }
+
+        return config;
+    }
+
+    pub fn save(&self) -> ()
+    {
+        let Some(path) = &self.path else { return };
+
+        let mut folders = Map::new();
+        for (folder, value) in &self.sidecar_folders
+        {
+            folders.insert(folder.clone(), Value::from(sidecar_to_str(*value)));
+        }
+
+        let mut namespaces = Map::new();
+        for (name, color) in &self.namespaces
+        {
+            namespaces.insert(name.clone(), Value::from(color_to_str(*color)));
+        }
+
+        let json = json!({
+            "sidecar": sidecar_to_str(self.sidecar),
+            "sidecar_folders": folders,
+            "aliases": self.aliases,
+            "implications": self.implications,
+            "write_implied": self.write_implied,
+            "namespaces": namespaces,
+            "saved_searches": self.saved_searches.iter()
+                .map(|(name, search)| json!({"name": name, "search": search})).collect::<Vec<Value>>(),
+            "search_history": self.search_history,
+        });
+
+        if let Some(parent) = path.parent() {let _ = std::fs::create_dir_all(parent);}
+        match serde_json::to_string_pretty(&json)
+        {
+            Ok(x) => if let Err(x) = std::fs::write(path, x) {println!("could not write config: {}", x);},
+            Err(x) => println!("could not write config: {}", x),
+        }
+    }
+
+    // folders inherit the setting of the closest parent folder that has one
+    pub fn sidecar_for(&self, folder: &String) -> Sidecar
+    {
+        let mut path = Some(Path::new(folder));
+        while let Some(x) = path
+        {
+            if let Some(value) = self.sidecar_folders.get(&folder_key(x)) {return *value;}
+            path = x.parent();
+        }
+
+        return self.sidecar;
+    }
+
+    pub fn folder_sidecar(&self, folder: &String) -> Option<Sidecar>
+    {
+        return self.sidecar_folders.get(&folder_key(Path::new(folder))).copied();
+    }
+
+    pub fn set_folder_sidecar(&mut self, folder: &String, value: Option<Sidecar>) -> ()
+    {
+        let key = folder_key(Path::new(folder));
+        match value
+        {
+            Some(x) => {self.sidecar_folders.insert(key, x);},
+            None => {self.sidecar_folders.remove(&key);},
+        }
+        self.save();
+    }
+
+    //////////////
+    // tag rules //
+    //////////////
+
+    pub fn canonical(&self, tag: &String) -> String
+    {
+        return self.aliases.get(tag).unwrap_or(tag).clone();
+    }
+
+    // every tag that should show up when searching for this one: its aliases,
+    // and the tags that imply it (directly or through other tags)
+    pub fn expand_tag(&self, tag: &String) -> HashSet<String>
+    {
+        let mut result = HashSet::new();
+        let mut todo = vec![self.canonical(tag)];
+
+        while let Some(tag) = todo.pop()
+        {
+            if !result.insert(tag.clone()) {continue;}
+
+            for (alias, target) in &self.aliases
+            {
+                if *target == tag {result.insert(alias.clone());}
+            }
+            for (source, implied) in &self.implications
+            {
+                if implied.iter().any(|x| self.canonical(x) == tag) {todo.push(self.canonical(source));}
+            }
+        }
+
+        return result;
+    }
+
+    // tags implied by this one, following chains of implications
+    pub fn implied_tags(&self, tag: &String) -> Vec<String>
+    {
+        let mut result = Vec::new();
+        let mut todo = vec![self.canonical(tag)];
+        let mut seen = HashSet::new();
+
+        while let Some(tag) = todo.pop()
+        {
+            if !seen.insert(tag.clone()) {continue;}
+            for implied in self.implications.get(&tag).into_iter().flatten()
+            {
+                let implied = self.canonical(implied);
+                if !result.contains(&implied) {result.push(implied.clone());}
+                todo.push(implied);
+            }
+        }
+
+        result.retain(|x| *x != self.canonical(tag));
+        return result;
+    }
+
+    pub fn set_alias(&mut self, alias: &String, tag: Option<&String>) -> ()
+    {
+        match tag
+        {
+            Some(x) if x != alias => {self.aliases.insert(alias.clone(), x.clone());},
+            _ => {self.aliases.remove(alias);},
+        }
+        self.save();
+    }
+
+    pub fn set_implication(&mut self, tag: &String, implied: &String, enabled: bool) -> ()
+    {
+        let list = self.implications.entry(tag.clone()).or_default();
+        list.retain(|x| x != implied);
+        if enabled && tag != implied {list.push(implied.clone());}
+        if list.len() == 0 {self.implications.remove(tag);}
+        self.save();
+    }
+
+    ////////////////
+    // namespaces //
+    ////////////////
+
+    // the namespace of a tag and the rest of it, only for namespaces the user defined
+    pub fn split_namespace<'a>(&self, tag: &'a str) -> Option<(&'a str, &'a str)>
+    {
+        let (namespace, value) = tag.split_once(':')?;
+        if !self.namespaces.contains_key(namespace) {return None;}
+        return Some((namespace, value));
+    }
+
+    pub fn set_namespace(&mut self, name: &String, color: Option<[u8; 3]>) -> ()
+    {
+        match color
+        {
+            Some(x) => {self.namespaces.insert(name.clone(), x);},
+            None => {self.namespaces.remove(name);},
+        }
+        self.save();
+    }
+
+    ////////////////////
+    // saved searches //
+    ////////////////////
+
+    // saving under an existing name replaces that search
+    pub fn save_search(&mut self, name: &String, search: &String) -> ()
+    {
+        match self.saved_searches.iter_mut().find(|x| x.0 == *name)
+        {
+            Some(x) => x.1 = search.clone(),
+            None => self.saved_searches.push((name.clone(), search.clone())),
+        }
+        self.save();
+    }
+
+    pub fn remove_search(&mut self, name: &String) -> ()
+    {
+        self.saved_searches.retain(|x| x.0 != *name);
+        self.save();
+    }
+
+    pub fn add_search_history(&mut self, search: &String) -> ()
+    {
+        let search = search.trim().to_string();
+        if search.len() == 0 || self.search_history.first() == Some(&search) {return;}
+
+        self.search_history.retain(|x| *x != search);
+        self.search_history.insert(0, search);
+        self.search_history.truncate(MAX_SEARCH_HISTORY);
+        self.save();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn set(tags: &[&str]) -> HashSet<String>
+    {
+        return tags.iter().map(|x| x.to_string()).collect();
+    }
+
+    #[test]
+    fn chained_implications()
+    {
+        let mut config = Config::new();
+        config.implications.insert("sitting".to_string(), vec!["pose".to_string()]);
+        config.implications.insert("pose".to_string(), vec!["figure".to_string()]);
+
+        assert_eq!(config.implied_tags(&"sitting".to_string()), vec!["pose".to_string(), "figure".to_string()]);
+        assert_eq!(config.implied_tags(&"figure".to_string()), Vec::<String>::new());
+        assert_eq!(config.expand_tag(&"figure".to_string()), set(&["figure", "pose", "sitting"]));
+        assert_eq!(config.expand_tag(&"sitting".to_string()), set(&["sitting"]));
+    }
+
+    // an implication that points back through an alias must not loop forever
+    #[test]
+    fn alias_implication_cycle()
+    {
+        let mut config = Config::new();
+        config.aliases.insert("kitty".to_string(), "cat".to_string());
+        config.implications.insert("cat".to_string(), vec!["kitty".to_string(), "animal".to_string()]);
+        config.implications.insert("animal".to_string(), vec!["cat".to_string()]);
+
+        assert_eq!(config.implied_tags(&"kitty".to_string()), vec!["animal".to_string()]);
+        assert_eq!(config.implied_tags(&"animal".to_string()), vec!["cat".to_string()]);
+        assert_eq!(config.expand_tag(&"animal".to_string()), set(&["animal", "cat", "kitty"]));
+        assert_eq!(config.expand_tag(&"kitty".to_string()), set(&["animal", "cat", "kitty"]));
+    }
+}
@@ -1,27 +1,76 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use core::cmp::Ordering;
 use serde_json::Value;
 use serde_json::json;
 
-use crate::data::{Data, Folder, Image};
+use crate::data::{Data, Folder, Image, Property};
+use crate::data::Backend;
 use crate::data::exiftool::Exiftool;
+use crate::data::native::Native;
+use crate::data::cache::{self, Cache};
+use crate::data::config::{Config, Sidecar};
+use crate::data::queue::WriteQueue;
+use crate::data::sidecar;
 use crate::data::image::Index;
+use crate::data::query::{self, Query, Field};
 use crate::shared::Shared;
 
+// seconds since 1970 to "yyyy-mm-dd hh:mm:ss" in utc
+fn format_date(seconds: u64) -> String
+{
+    let (days, time) = ((seconds / 86400) as i64, seconds % 86400);
+
+    // days to a civil date, from howard hinnant's date algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 {mp + 3} else {mp - 9};
+    let year = yoe + era * 400 + if month <= 2 {1} else {0};
+
+    return format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60);
+}
+
 impl Data 
 {
     pub fn new() -> Result<Data, String> 
     {
-        let exif = Exiftool::new();
         let data = Vec::<Folder>::new();
-        let taglist = HashMap::<String, Vec<Index>>::new();
 
-        match exif
+        // REF_VIEWER_BACKEND=native|exiftool forces a backend, by default exiftool is used when installed
+        let choice = std::env::var("REF_VIEWER_BACKEND").unwrap_or_default();
+        let backend: Box<dyn Backend> = match choice.as_str()
         {
-            Some(x) => return Ok(Data {folders:data, exif:x, taglist:taglist}),
-            None => return Err("exif not found on system".to_owned()),
-        }
+            "native" => Box::new(Native::new()),
+            "exiftool" => match Exiftool::new()
+            {
+                Some(x) => Box::new(x),
+                None => return Err("exif not found on system".to_owned()),
+            },
+            _ => match Exiftool::new()
+            {
+                Some(x) => Box::new(x),
+                None => 
+                {
+                    println!("exiftool not found, using the native metadata backend.");
+                    Box::new(Native::new())
+                },
+            },
+        };
+
+        return Ok(Data {folders:data, 
+                        queue:WriteQueue::new(backend), 
+                        writes_pending:0,
+                        write_failures:Vec::new(),
+                        cache:Cache::load(), 
+                        config:Config::load(), 
+                        taglist:HashMap::new(),
+                        artistlist:HashMap::new(),
+                        domainlist:HashMap::new(),
+                        noteslist:HashMap::new()});
     }
 
     ///////////////////
@@ -66,8 +115,8 @@ impl Data
             self.folders.remove(i);
         };
 
-        data_shared.update_search(self);
         self.build_tags();
+        data_shared.update_search(self);
     }
 
     pub fn open_folders(&mut self, paths: Vec::<String>) -> Option<Index>
@@ -102,6 +151,7 @@ impl Data
         }
 
         self.build_tags();
+        self.cache.save();
         return index;
     }
 
@@ -121,7 +171,7 @@ impl Data
         return vector;
     }
 
-    fn construct_image(info : &serde_json::value::Value) -> Result<Image, &str>
+    fn construct_image(info : &serde_json::value::Value, storage: Sidecar) -> Result<Image, &str>
     {
         let empty = &json!("");
         let file = info.get("SourceFile").unwrap_or(empty).as_str().unwrap_or("");
@@ -136,13 +186,23 @@ impl Data
         let str_notes = info.get("UserComment").unwrap_or(empty).as_str().unwrap_or("");
         let size = info.get("ImageSize").unwrap_or(empty).as_str().unwrap_or("");
         
-        let artists = Self::read_json(&str_artists.to_string());
-        let links = Self::read_json(&str_links.to_string());
-        let tags = Self::read_json(&str_tags.to_string());
-        let notes = str_notes.replace("\\\"", "\"");
+        let mut artists = Self::read_json(&str_artists.to_string());
+        let mut links = Self::read_json(&str_links.to_string());
+        let mut tags = Self::read_json(&str_tags.to_string());
+        let mut notes = str_notes.replace("\\\"", "\"");
+
+        // a folder that went back to storing tags in the images can still have old sidecar files next to them
+        let sidecar = if storage == Sidecar::Off {None} else {sidecar::read(&file.to_string())};
+        if let Some(sidecar) = sidecar
+        {
+            if let Some(x) = sidecar.artists {artists = x;}
+            if let Some(x) = sidecar.links {links = x;}
+            if let Some(x) = sidecar.tags {tags = x;}
+            if let Some(x) = sidecar.notes {notes = x;}
+        }
 
 
-        let image = Image::new(
+        let mut image = Image::new(
             file.to_string(), 
             artists, 
             size.to_string(), 
@@ -151,6 +211,28 @@ impl Data
             notes,
         );
 
+        let number = |key: &str| info.get(key).and_then(|x| x.as_u64());
+        let (w, h) = size.split_once('x').unwrap_or(("0", "0"));
+        image.width = number("ImageWidth").unwrap_or(w.trim().parse().unwrap_or(0)) as u32;
+        image.height = number("ImageHeight").unwrap_or(h.trim().parse().unwrap_or(0)) as u32;
+
+        // orientations 5 to 8 turn the image a quarter, the way it is shown
+        if (5..=8).contains(&number("Orientation").unwrap_or(1))
+        {
+            (image.width, image.height) = (image.height, image.width);
+        }
+
+        let webp_flags = info.get("WebP_Flags").and_then(|x| x.as_str()).unwrap_or("");
+        image.animated = number("FrameCount").unwrap_or(0) > 1 || number("AnimationFrames").unwrap_or(0) > 1 ||
+                         webp_flags.contains("Animation");
+
+        // read from the file system, so they are the same for both backends
+        if let Some((mtime, file_size)) = cache::file_stamp(Path::new(&image.file))
+        {
+            image.file_size = file_size;
+            image.modified = format_date(mtime / 1000);
+        }
+
         return Ok(image)
     }
 
@@ -174,27 +256,71 @@ impl Data
             collapsed: false,
             images: Vec::new()};
 
-        let output = match self.exif.get_folder_data(path)
-        {
-            Ok(x) => x,
-            Err(_x) => return Err("Error with exiftool".to_string()),
-        };
+        let files = Self::list_images(Path::new(path));
+        let mut infos = Vec::<Value>::new();
+        let mut missing = Vec::<String>::new();
 
-        if output.len() == 0 
+        for file in &files
         {
-            self.folders.push(folder);
-            return Ok(());
+            let cached = match cache::file_stamp(Path::new(file))
+            {
+                Some((mtime, size)) => self.cache.get(file, mtime, size),
+                None => None,
+            };
+
+            match cached
+            {
+                Some(x) => infos.push(x.clone()),
+                None => missing.push(file.clone()),
+            }
         }
 
-        let json = match serde_json::from_str::<Value>(&output)
+        if missing.len() > 0
         {
-            Ok(x) => x,
-            Err(_x) => return Err("Error with json output".to_string()),
-        };
+            // nothing cached yet, a single folder query is a lot faster than listing every file
+            let folder_path = path.clone();
+            let output = if missing.len() == files.len() {self.queue.read(move |x| x.get_folder_data(&folder_path))}
+                         else {self.queue.read(move |x| x.get_files_data(&missing))};
+
+            let output = match output
+            {
+                Some(Ok(x)) => x,
+                _ => return Err("Error with exiftool".to_string()),
+            };
+
+            if output.len() > 0
+            {
+                let json = match serde_json::from_str::<Value>(&output)
+                {
+                    Ok(x) => x,
+                    Err(_x) => return Err("Error with json output".to_string()),
+                };
+
+                for value in json.as_array().unwrap()
+                {
+                    let source = value.get("SourceFile").and_then(|x| x.as_str()).unwrap_or("");
+                    let Some(name) = Path::new(source).file_name() else { continue };
+                    let file = Path::new(path).join(name).to_string_lossy().into_owned();
+
+                    if let Some((mtime, size)) = cache::file_stamp(Path::new(&file))
+                    {
+                        self.cache.insert(file, mtime, size, value.clone());
+                    }
+                    infos.push(value.clone());
+                }
+            }
+        }
 
-        for value in json.as_array().unwrap()
+        self.cache.prune_folder(Path::new(path), &files.into_iter().collect());
+        infos.sort_by_cached_key(|x| {
+            let source = x.get("SourceFile").and_then(|x| x.as_str()).unwrap_or("");
+            Path::new(source).file_name().unwrap_or_default().to_os_string()
+        });
+
+        let storage = self.config.sidecar_for(&folder.path);
+        for value in &infos
         {
-            match Self::construct_image(value)
+            match Self::construct_image(value, storage)
             {
                 Ok(x) => folder.images.push(x),
                 Err(_x) => println!("error with image"),
@@ -205,83 +331,196 @@ impl Data
         return Ok(());
     } 
 
-    pub fn build_vector(&self, tags: Vec<String>, itags: Vec<String>) -> Vec<Vec<Index>>
+    pub fn list_images(path: &Path) -> Vec<String>
     {
-        let mut imglist = Vec::new();
-        for _ in 0..self.folders.len() {imglist.push(Vec::<Index>::new());}
+        let extensions = ["jpg", "jpeg", "png", "tga", "tiff", "webp", "gif"];
+        let Ok(entries) = std::fs::read_dir(path) else { return vec![] };
+
+        entries.flatten().filter_map(|entry| {
+            let file = entry.path();
+            if !file.is_file() {return None;}
 
-        match tags.len()
+            let ext = file.extension()?.to_str()?.to_lowercase();
+            if !extensions.contains(&ext.as_str()) {return None;}
+            return Some(file.to_string_lossy().into_owned());
+        }).collect()
+    }
+
+    fn query_set(&self, query: &Query) -> HashSet<Index>
+    {
+        match query
         {
-            0 => 
+            Query::All => return self.all_indexes(),
+            Query::Term(Field::Any, text) =>
             {
-                for (f, folder) in self.folders.iter().enumerate()
+                let mut set = self.query_set(&Query::Term(Field::Tag, text.clone()));
+                set.extend(self.query_set(&Query::Term(Field::Artist, text.clone())));
+                return set;
+            },
+            Query::Term(Field::Tag, tag) =>
+            {
+                // aliases and implications can point at tags no image has, so expand before looking them up
+                let tag = tag.to_lowercase();
+                let mut terms = HashSet::new();
+                if !query::is_glob(&tag) {terms.insert(tag.clone());}
+                else
                 {
-                    for i in 0..folder.images.len()
-                    {
-                        imglist[f].push(Index{folder:f, image:i});
-                    }
-                } 
-            }
-            _ =>
+                    let rules = self.config.aliases.keys().chain(self.config.implications.keys()).cloned();
+                    let tags = self.taglist.keys().map(|x| x.to_lowercase());
+                    terms.extend(tags.chain(rules).filter(|x| query::glob_match(&tag, x)));
+                }
+                let expanded: HashSet<String> = terms.iter().flat_map(|x| self.config.expand_tag(x)).collect();
+
+                // other tools can write tags with capitals, so compare lowercased like artists
+                let mut set = HashSet::new();
+                for (tag, indexes) in &self.taglist
+                {
+                    if expanded.contains(&tag.to_lowercase()) {set.extend(indexes.iter().cloned());}
+                }
+                return set;
+            },
+            Query::Term(Field::Artist, text) =>
+            {
+                let text = text.to_lowercase();
+                let mut set = HashSet::new();
+                for (artist, indexes) in &self.artistlist
+                {
+                    if query::glob_match(&text, &artist.to_lowercase()) {set.extend(indexes.iter().cloned());}
+                }
+                return set;
+            },
+            Query::Term(Field::Source, text) =>
             {
-                let mut alltags = Vec::new();
+                // "pixiv" finds pixiv.net, wildcards match against the whole domain
+                let text = text.to_lowercase();
+                let glob = query::is_glob(&text);
+                let mut set = HashSet::new();
+                for (domain, indexes) in &self.domainlist
+                {
+                    let found = if glob {query::glob_match(&text, domain)} else {domain.contains(&text)};
+                    if found {set.extend(indexes.iter().cloned());}
+                }
+                return set;
+            },
+            Query::Term(Field::Notes, text) =>
+            {
+                let text = text.to_lowercase();
+                let mut set = HashSet::new();
 
-                for tag in &tags
+                // wildcards match single words through the index, anything else is searched as text
+                if query::is_glob(&text)
                 {
-                    match self.taglist.get(tag)
+                    for (word, indexes) in &self.noteslist
                     {
-                        Some(vec) => {alltags.append(&mut vec.clone());},
-                        None => (),
+                        if query::glob_match(&text, word) {set.extend(indexes.iter().cloned());}
                     }
+                    return set;
                 }
 
-                let mut tagcounts = HashMap::new();
-                for item in alltags {*tagcounts.entry(item).or_insert(0) += 1;}
-                for (tag, count) in tagcounts {if count == tags.len() {imglist[tag.folder].push(tag);}}
-
-                for folder in &mut imglist
+                for (f, folder) in self.folders.iter().enumerate()
                 {
-                    folder.sort_by(|a, b| {
-                        if a.folder < b.folder {Ordering::Less} 
-                        else if a.folder > b.folder {Ordering::Greater} 
-                        else 
-                        {
-                            if a.image < b.image {Ordering::Less} 
-                            else if a.image > b.image {Ordering::Greater} 
-                            else {Ordering::Equal}                    
-                        }
-                    });
+                    for (i, image) in folder.images.iter().enumerate()
+                    {
+                        if image.notes.to_lowercase().contains(&text) {set.insert(Index{folder:f, image:i});}
+                    }
                 }
-            }
-        }
-
-        
-        for tag in itags
-        {
-            match self.taglist.get(&tag)
+                return set;
+            },
+            Query::Term(field, value) =>
             {
-                Some(vec) => 
+                let (compare, rest) = query::split_compare(value);
+                let mut set = HashSet::new();
+
+                for (f, folder) in self.folders.iter().enumerate()
                 {
-                    for index in vec
+                    for (i, image) in folder.images.iter().enumerate()
                     {
-                        for folder in &mut imglist
+                        let found = match field
                         {
-                            match folder.iter().position(|x| x == index)
+                            Field::Width => query::compare(compare, image.width, rest.parse().unwrap_or(0)),
+                            Field::Height => query::compare(compare, image.height, rest.parse().unwrap_or(0)),
+                            Field::FileSize => query::compare(compare, image.file_size, query::parse_filesize(rest).unwrap_or(0)),
+                            Field::Animated => query::parse_bool(value) == Some(image.animated),
+                            Field::Orientation => match value.as_str()
+                            {
+                                "landscape" => image.width > image.height,
+                                "portrait" => image.width < image.height,
+                                _ => image.width == image.height,
+                            },
+                            Field::Ext =>
                             {
-                                Some(index) => {folder.remove(index);},
-                                None => {},
-                            };
-                        }
+                                let ext = image.extension();
+                                let ext = if ext == "jpeg" {"jpg".to_string()} else {ext};
+                                let value = if value == "jpeg" {"jpg"} else {value.as_str()};
+                                query::glob_match(value, &ext)
+                            },
+                            // dates compare as text, "2025-01" sorts before "2025-01-02"
+                            Field::Modified if query::is_glob(value) => query::glob_match(value, &image.modified),
+                            Field::Modified if compare == query::Compare::Equal => image.modified.starts_with(rest),
+                            Field::Modified => query::compare(compare, &image.modified[..rest.len().min(image.modified.len())], rest),
+                            _ => false,
+                        };
+                        if found {set.insert(Index{folder:f, image:i});}
                     }
-                },
-                None => (),
-            }
+                }
+                return set;
+            },
+            Query::Not(inner) =>
+            {
+                let excluded = self.query_set(inner);
+                let mut set = self.all_indexes();
+                set.retain(|x| !excluded.contains(x));
+                return set;
+            },
+            Query::And(parts) =>
+            {
+                let mut set = self.query_set(&parts[0]);
+                for part in &parts[1..]
+                {
+                    if set.len() == 0 {break;}
+                    let other = self.query_set(part);
+                    set.retain(|x| other.contains(x));
+                }
+                return set;
+            },
+            Query::Or(parts) =>
+            {
+                let mut set = HashSet::new();
+                for part in parts {set.extend(self.query_set(part));}
+                return set;
+            },
         }
+    }
+
+    // amount of images a search finds, None when it can't be parsed
+    pub fn count_matches(&self, search: &String) -> Option<usize>
+    {
+        let query = query::parse(search).ok()?;
+        return Some(self.query_set(&query).len());
+    }
+
+    fn all_indexes(&self) -> HashSet<Index>
+    {
+        let mut set = HashSet::new();
+        for (f, folder) in self.folders.iter().enumerate()
+        {
+            for i in 0..folder.images.len() {set.insert(Index{folder:f, image:i});}
+        }
+        return set;
+    }
+
+    pub fn build_vector(&self, query: &Query) -> Vec<Vec<Index>>
+    {
+        let mut imglist = Vec::new();
+        for _ in 0..self.folders.len() {imglist.push(Vec::<Index>::new());}
+
+        for index in self.query_set(query) {imglist[index.folder].push(index);}
+        for folder in &mut imglist {folder.sort_by(|a, b| a.image.cmp(&b.image));}
 
         return imglist;
     }
     
-    fn get_path_index(&self, path: &String) -> Option<Index>
+    pub fn get_path_index(&self, path: &String) -> Option<Index>
     {
         let img_folder = Path::new(path).parent().unwrap();
         for (f, folder) in self.folders.iter().enumerate()
@@ -300,22 +539,43 @@ impl Data
     // taglist //
     /////////////
 
+    // "https://www.example.com/a/b" -> "example.com"
+    pub fn link_domain(link: &String) -> String
+    {
+        let link = link.to_lowercase();
+        let rest = link.split_once("://").map(|x| x.1).unwrap_or(&link);
+        let host = rest.split(|c| c == '/' || c == '?').next().unwrap_or("");
+        return host.trim_start_matches("www.").to_string();
+    }
+
     fn build_tags(&mut self)
     {
         self.taglist.clear();
+        self.artistlist.clear();
+        self.domainlist.clear();
+        self.noteslist.clear();
 
         for (f, folder) in self.folders.iter().enumerate()
         {
             for (i, image) in folder.images.iter().enumerate()
             {
                 let index = Index{folder:f, image:i};
-                for tag in &image.artists
+                for artist in &image.artists
                 {
-                    self.taglist.entry(tag.clone()).or_default().push(index.clone());  
+                    self.artistlist.entry(artist.clone()).or_default().push(index.clone());
                 }
                 for tag in &image.tags
                 {
-                    self.taglist.entry(tag.clone()).or_default().push(index.clone());  
+                    self.taglist.entry(tag.clone()).or_default().push(index.clone());
+                }
+                // an image can have several links on the same domain
+                for link in &image.links
+                {
+                    Self::add_taglist(&mut self.domainlist, &index, &Self::link_domain(link));
+                }
+                for word in query::words(&image.notes)
+                {
+                    self.noteslist.entry(word).or_default().push(index.clone());
                 }
             }
         }
@@ -382,70 +642,328 @@ impl Data
         return result;
     }
 
-    pub fn del_tag(&mut self, img_index: &Index, tag: &String) -> ()
+    // queues a write of one property to the image file, or of the whole image to its sidecar when those are used
+    fn write_property(&mut self, img_index: &Index, property: Property) -> ()
+    {
+        let folder = &mut self.folders[img_index.folder];
+        let sidecar = self.config.sidecar_for(&folder.path);
+        let img = &mut folder.images[img_index.image];
+
+        img.pending_writes += 1;
+        img.failed_writes.retain(|x| *x != property);
+        self.writes_pending += 1;
+
+        let file = img.file.clone();
+        match sidecar
+        {
+            Sidecar::Off => (),
+            kind => 
+            {
+                let (artists, links, tags, notes) = (img.artists.clone(), img.links.clone(), img.tags.clone(), img.notes.clone());
+                self.queue.write(file.clone(), property, move |_| sidecar::write(&file, kind, &artists, &links, &tags, &notes));
+                return;
+            }
+        }
+
+        match property
+        {
+            Property::Artists => 
+            {
+                let value = Self::build_string(&img.artists);
+                self.queue.write(file.clone(), property, move |x| x.set_artist(&file, &value));
+            },
+            Property::Links => 
+            {
+                let value = Self::build_string(&img.links);
+                self.queue.write(file.clone(), property, move |x| x.set_link(&file, &value));
+            },
+            Property::Tags => 
+            {
+                let value = Self::build_string(&img.tags);
+                self.queue.write(file.clone(), property, move |x| x.set_tags(&file, &value));
+            },
+            Property::Notes => 
+            {
+                let value = img.notes.replace("\"", "\\\"");
+                self.queue.write(file.clone(), property, move |x| x.set_notes(&file, &value));
+            },
+        }
+    }
+
+    // handles finished writes, returns true while writes are still pending
+    pub fn poll_writes(&mut self) -> bool
+    {
+        if self.writes_pending == 0 {return false;}
+
+        for done in self.queue.poll()
+        {
+            self.writes_pending -= 1;
+            let Some(index) = self.get_path_index(&done.file) else { continue };
+            let img = &mut self.folders[index.folder].images[index.image];
+            img.pending_writes = img.pending_writes.saturating_sub(1);
+
+            match done.result
+            {
+                Ok(_) => (),
+                Err(x) => 
+                {
+                    println!("failed to write {}: {}", done.file, x);
+                    if !img.failed_writes.contains(&done.property) {img.failed_writes.push(done.property);}
+                    img.write_error = Some(x);
+                },
+            }
+
+            if img.failed_writes.len() == 0 {img.write_error = None;}
+
+            let listed = self.write_failures.contains(&done.file);
+            if img.write_error.is_some() && !listed {self.write_failures.push(done.file);}
+            else if img.write_error.is_none() && listed {self.write_failures.retain(|x| *x != done.file);}
+        }
+
+        return self.writes_pending > 0;
+    }
+
+    pub fn retry_writes(&mut self, img_index: &Index) -> ()
+    {
+        let failed = self.folders[img_index.folder].images[img_index.image].failed_writes.clone();
+        for property in failed {self.write_property(img_index, property);}
+    }
+
+    pub fn get_failed_writes(&self) -> Vec<Index>
+    {
+        return self.write_failures.iter().filter_map(|x| self.get_path_index(x)).collect();
+    }
+
+    pub fn del_tag(&mut self, img_index: &Index, tag: &String) -> bool
     {
         let img = &mut self.folders[img_index.folder].images[img_index.image];
+        if !img.remove_tag(tag) {return false;}
 
-        img.remove_tag(tag);
         Self::rem_taglist(&mut self.taglist, img_index, tag);
+        self.write_property(img_index, Property::Tags);
+        return true;
+    }
+
+    // returns every tag that was added, the implied ones included when with_implied is set
+    pub fn add_tag(&mut self, img_index: &Index, tag: &String, with_implied: bool) -> Vec<String>
+    {
+        let tag = tag.to_lowercase();
+        let implied = if with_implied && self.config.write_implied {self.config.implied_tags(&tag)} else {Vec::new()};
 
-        let output = Self::build_string(&img.tags);
-        let _ = self.exif.set_tags(&img.file, &output);
+        let img = &mut self.folders[img_index.folder].images[img_index.image];
+        if !img.add_tag(&tag) {return Vec::new();}
+        Self::add_taglist(&mut self.taglist, img_index, &tag);
+        let mut added = vec![tag];
+
+        for tag in implied
+        {
+            if img.add_tag(&tag)
+            {
+                Self::add_taglist(&mut self.taglist, img_index, &tag);
+                added.push(tag);
+            }
+        }
+
+        self.write_property(img_index, Property::Tags);
+        return added;
     }
 
-    pub fn add_tag(&mut self, img_index: &Index, tag: &String) -> ()
+    pub fn del_link(&mut self, img_index: &Index, link: &String) -> bool
     {
         let img = &mut self.folders[img_index.folder].images[img_index.image];
-        
-        img.add_tag(tag);
-        Self::add_taglist(&mut self.taglist, img_index, tag);
+        if !img.remove_link(link) {return false;}
 
-        let output = Self::build_string(&img.tags);
-        let _ = self.exif.set_tags(&img.file, &output);
+        // other links of the image can still be on the same domain
+        let domain = Self::link_domain(link);
+        if !img.links.iter().any(|x| Self::link_domain(x) == domain)
+        {
+            Self::rem_taglist(&mut self.domainlist, img_index, &domain);
+        }
+        self.write_property(img_index, Property::Links);
+        return true;
     }
 
-    pub fn del_link(&mut self, img_index: &Index, link: &String) -> ()
+    pub fn add_link(&mut self, img_index: &Index, link: &String) -> bool
     {
         let img = &mut self.folders[img_index.folder].images[img_index.image];
-        img.remove_link(link);
-        let output = Self::build_string(&img.links);
-        let _ = self.exif.set_link(&img.file, &output);
+        if !img.add_link(link) {return false;}
+
+        Self::add_taglist(&mut self.domainlist, img_index, &Self::link_domain(link));
+        self.write_property(img_index, Property::Links);
+        return true;
     }
 
-    pub fn add_link(&mut self, img_index: &Index, link: &String) -> ()
+    pub fn del_artist(&mut self, img_index: &Index, artist: &String) -> bool
     {
         let img = &mut self.folders[img_index.folder].images[img_index.image];
-        img.add_link(link);
-        let output = Self::build_string(&img.links);
-        let _ = self.exif.set_link(&img.file, &output);
+        if !img.remove_artist(artist) {return false;}
+
+        Self::rem_taglist(&mut self.artistlist, img_index, artist);
+        self.write_property(img_index, Property::Artists);
+        return true;
     }
 
-    pub fn del_artist(&mut self, img_index: &Index, artist: &String) -> ()
+    pub fn add_artist(&mut self, img_index: &Index, artist: &String) -> bool
     {
         let img = &mut self.folders[img_index.folder].images[img_index.image];
+        if !img.add_artist(artist) {return false;}
+
+        Self::add_taglist(&mut self.artistlist, img_index, artist);
+        self.write_property(img_index, Property::Artists);
+        return true;
+    }
 
-        img.remove_artist(artist);
-        Self::rem_taglist(&mut self.taglist, img_index, artist);
+    fn list_counts(list: &HashMap::<String, Vec<Index>>) -> Vec<(String, usize)>
+    {
+        let mut counts: Vec<(String, usize)> = list.iter().map(|(x, indexes)| (x.clone(), indexes.len())).collect();
+        counts.sort();
+        return counts;
+    }
 
-        let output = Self::build_string(&img.artists);
-        let _ = self.exif.set_artist(&img.file, &output);
+    pub fn tag_counts(&self) -> Vec<(String, usize)>
+    {
+        return Self::list_counts(&self.taglist);
     }
 
-    pub fn add_artist(&mut self, img_index: &Index, artist: &String) -> ()
+    pub fn artist_counts(&self) -> Vec<(String, usize)>
+    {
+        return Self::list_counts(&self.artistlist);
+    }
+
+    // removes and adds tags on one image with a single write, tags keep their case so undo can put them back exactly.
+    // returns the tags that were actually removed and added
+    pub fn change_tags(&mut self, img_index: &Index, remove: &Vec<String>, add: &Vec<String>) -> (Vec<String>, Vec<String>)
     {
         let img = &mut self.folders[img_index.folder].images[img_index.image];
+        let removed: Vec<String> = remove.iter().filter(|x| img.remove_tag(x)).cloned().collect();
+        let mut added = Vec::new();
+        for tag in add
+        {
+            if img.tags.contains(tag) {continue;}
+            img.tags.push(tag.clone());
+            added.push(tag.clone());
+        }
+        img.tags.sort();
 
-        img.add_artist(artist);
-        Self::add_taglist(&mut self.taglist, img_index, artist);
-        
-        let output = Self::build_string(&img.artists);
-        let _ = self.exif.set_artist(&img.file, &output);
+        for tag in &removed {Self::rem_taglist(&mut self.taglist, img_index, tag);}
+        for tag in &added {Self::add_taglist(&mut self.taglist, img_index, tag);}
+        if removed.len() > 0 || added.len() > 0 {self.write_property(img_index, Property::Tags);}
+        return (removed, added);
     }
 
-    pub fn set_notes(&mut self, img_index: &Index) -> ()
+    // removes the tags from every image that has them and adds the replacement if there is one.
+    // used for renaming, merging and deleting, every image gets a single write. returns what changed per image
+    pub fn replace_tags(&mut self, tags: &Vec<String>, replacement: Option<&String>) -> Vec<(Index, Vec<String>, Vec<String>)>
+    {
+        let mut changed = HashSet::new();
+        for tag in tags
+        {
+            let Some(indexes) = self.taglist.get(tag) else { continue };
+            changed.extend(indexes.iter().cloned());
+        }
+
+        let add: Vec<String> = replacement.into_iter().map(|x| x.to_lowercase()).collect();
+        let mut changes = Vec::new();
+        for index in changed
+        {
+            let (removed, added) = self.change_tags(&index, tags, &add);
+            if removed.len() > 0 || added.len() > 0 {changes.push((index, removed, added));}
+        }
+        return changes;
+    }
+
+    // saves the notes as they are in the image, returns what was saved before
+    pub fn set_notes(&mut self, img_index: &Index) -> String
     {
         let img = &mut self.folders[img_index.folder].images[img_index.image];
-        let altered_notes = img.notes.replace("\"", "\\\"");
-        let _ = self.exif.set_notes(&img.file, &altered_notes);
+        let previous = std::mem::replace(&mut img.saved_notes, img.notes.clone());
+
+        for word in query::words(&previous) {Self::rem_taglist(&mut self.noteslist, img_index, &word);}
+        for word in query::words(&img.notes) {Self::add_taglist(&mut self.noteslist, img_index, &word);}
+
+        self.write_property(img_index, Property::Notes);
+        return previous;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn image(file: &str, artists: &[&str], tags: &[&str]) -> Image
+    {
+        let list = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        return Image::new(file.to_string(), list(artists), "10x10".to_string(), Vec::new(), list(tags), String::new());
+    }
+
+    fn test_data(images: Vec<Image>) -> Data
+    {
+        let folder = Folder{path: "/refs".to_string(), btn_path: "refs".to_string(), collapsed: false, images: images};
+        let mut data = Data{queue: WriteQueue::new(Box::new(Native::new())),
+                            writes_pending: 0,
+                            write_failures: Vec::new(),
+                            cache: Cache::new(),
+                            config: Config::new(),
+                            folders: vec![folder],
+                            taglist: HashMap::new(),
+                            artistlist: HashMap::new(),
+                            domainlist: HashMap::new(),
+                            noteslist: HashMap::new()};
+        data.build_tags();
+        return data;
+    }
+
+    fn count(data: &Data, search: &str) -> Option<usize>
+    {
+        return data.count_matches(&search.to_string());
+    }
+
+    #[test]
+    fn mixed_case_tags()
+    {
+        let data = test_data(vec![image("/refs/a.png", &["Someone"], &["Hand", "pose:Sitting"]),
+                                  image("/refs/b.png", &[], &["hand"])]);
+
+        assert_eq!(count(&data, "Hand"), Some(2));
+        assert_eq!(count(&data, "tag:Hand"), Some(2));
+        assert_eq!(count(&data, "tag:hand"), Some(2));
+        assert_eq!(count(&data, "Han*"), Some(2));
+        assert_eq!(count(&data, "pose:sitting"), Some(1));
+        assert_eq!(count(&data, "artist:someone"), Some(1));
+        assert_eq!(count(&data, "-Hand"), Some(0));
+    }
+
+    #[test]
+    fn mixed_case_aliases()
+    {
+        let mut data = test_data(vec![image("/refs/a.png", &[], &["Feet"])]);
+        data.config.aliases.insert("foot".to_string(), "feet".to_string());
+        data.config.implications.insert("feet".to_string(), vec!["anatomy".to_string()]);
+
+        assert_eq!(count(&data, "foot"), Some(1));
+        assert_eq!(count(&data, "anatomy"), Some(1));
+    }
+
+    #[test]
+    fn replace_and_restore_tags()
+    {
+        let mut data = test_data(vec![image("/refs/a.png", &[], &["Hand", "pose"]),
+                                      image("/refs/b.png", &[], &["hand"]),
+                                      image("/refs/c.png", &[], &["feet"])]);
+
+        let tags = vec!["Hand".to_string(), "hand".to_string()];
+        let mut changes = data.replace_tags(&tags, Some(&"hands".to_string()));
+        changes.sort_by_key(|x| x.0.image);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].1, vec!["Hand".to_string()]);
+        assert_eq!(count(&data, "hand"), Some(0));
+        assert_eq!(count(&data, "hands"), Some(2));
+
+        // undo puts back the tags exactly as they were written
+        for (index, removed, added) in &changes {data.change_tags(index, added, removed);}
+        assert_eq!(data.folders[0].images[0].tags, vec!["Hand".to_string(), "pose".to_string()]);
+        assert_eq!(count(&data, "hands"), Some(0));
+        assert_eq!(count(&data, "hand"), Some(2));
     }
 }
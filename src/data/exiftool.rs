@@ -4,8 +4,13 @@ use std::io::BufReader;
 use std::sync::mpsc;
 use std::thread;
 
+use crate::data::Backend;
+
 /////////////////////////
 
+const READ_ARGS: &str = "\n-fast2\n-Artist\n-PageName\n-ImageDescription\n-ImageSize\n-UserComment\n\
+                         -ImageWidth\n-ImageHeight\n-Orientation#\n-FrameCount\n-AnimationFrames\n-WebP_Flags\n-json\n";
+
 pub struct Exiftool
 {
     exif: std::process::Child,
@@ -21,7 +26,7 @@ impl Drop for Exiftool {
     fn drop(&mut self) 
     {
         let command = "-stay_open\nFalse\n".to_string();
-        self.stdin.write(command.as_bytes()).unwrap();
+        self.stdin.write_all(command.as_bytes()).unwrap();
 
         let _ = self.stop_tx.send("".to_string());
     }
@@ -112,21 +117,52 @@ impl Exiftool
                 }
             }
         })
-    }  
+    }
 
-    pub fn get_folder_data(&mut self, path: &String) ->  Result<String, String>
+    // exiftool reports failed writes on stdout, next to the errors it prints on stderr
+    fn check_write(output: String) -> Result<String, String>
     {
-        let mut command = "\n-FileOrder8\n-fast2\n-FileName\n-Artist\n-PageName\n-ImageDescription\n-ImageSize\n-UserComment\n-json\n".to_string();
+        if output.contains("weren't updated") || output.contains("Error")
+        {
+            return Err(output.trim().to_string());
+        }
+        return Ok(output);
+    }
+}
+
+impl Backend for Exiftool
+{
+    fn get_folder_data(&mut self, path: &String) ->  Result<String, String>
+    {
+        let mut command = "\n-FileOrder8\n-FileName".to_string();
+        command.push_str(READ_ARGS);
         command.push_str("-ext\njpg\n-ext\njpeg\n-ext\npng\n-ext\ntga\n-ext\ntiff\n-ext\nwebp\n-ext\ngif\n");
         command.push_str(path);
         command.push_str("\n-execute\n");
 
-        self.stdin.write(command.as_bytes()).unwrap();
+        self.stdin.write_all(command.as_bytes()).unwrap();
         let result = self.thd_rx.recv().unwrap();
         return Ok(result);
     }   
 
-    pub fn set_notes(&mut self, path: &String, notes: &String) ->  Result<String, String>
+    fn get_files_data(&mut self, paths: &Vec<String>) ->  Result<String, String>
+    {
+        let mut command = "\n-FileName".to_string();
+        command.push_str(READ_ARGS);
+
+        for path in paths
+        {
+            command.push_str(path);
+            command.push_str("\n");
+        }
+        command.push_str("-execute\n");
+
+        self.stdin.write_all(command.as_bytes()).unwrap();
+        let result = self.thd_rx.recv().unwrap();
+        return Ok(result);
+    }   
+
+    fn set_notes(&mut self, path: &String, notes: &String) ->  Result<String, String>
     {
         let mut command = "-overwrite_original\n-m\n-UserComment=\"".to_string();
         command.push_str(notes);
@@ -134,12 +170,12 @@ impl Exiftool
         command.push_str(path);
         command.push_str("\n-execute\n");
 
-        self.stdin.write(command.as_bytes()).unwrap();
+        self.stdin.write_all(command.as_bytes()).unwrap();
         let result = self.thd_rx.recv().unwrap();
-        return Ok(result);
+        return Self::check_write(result);
     }   
 
-    pub fn set_tags(&mut self, path: &String, tag: &String) ->  Result<String, String>
+    fn set_tags(&mut self, path: &String, tag: &String) ->  Result<String, String>
     {
         let mut command = "-overwrite_original\n-m\n-ImageDescription=".to_string();
         command.push_str(tag);
@@ -147,12 +183,12 @@ impl Exiftool
         command.push_str(path);
         command.push_str("\n-execute\n");
 
-        self.stdin.write(command.as_bytes()).unwrap();
+        self.stdin.write_all(command.as_bytes()).unwrap();
         let result = self.thd_rx.recv().unwrap();
-        return Ok(result);
+        return Self::check_write(result);
     }   
 
-    pub fn set_link(&mut self, path: &String, tag: &String) ->  Result<String, String>
+    fn set_link(&mut self, path: &String, tag: &String) ->  Result<String, String>
     {
         let mut command = "-overwrite_original\n-m\n-PageName=".to_string();
         command.push_str(tag);
@@ -160,12 +196,12 @@ impl Exiftool
         command.push_str(path);
         command.push_str("\n-execute\n");
 
-        self.stdin.write(command.as_bytes()).unwrap();
+        self.stdin.write_all(command.as_bytes()).unwrap();
         let result = self.thd_rx.recv().unwrap();
-        return Ok(result);
+        return Self::check_write(result);
     }   
 
-    pub fn set_artist(&mut self, path: &String, tag: &String) ->  Result<String, String>
+    fn set_artist(&mut self, path: &String, tag: &String) ->  Result<String, String>
     {
         let mut command = "-overwrite_original\n-m\n-Artist=".to_string();
         command.push_str(tag);
@@ -173,8 +209,8 @@ impl Exiftool
         command.push_str(path);
         command.push_str("\n-execute\n");
 
-        self.stdin.write(command.as_bytes()).unwrap();
+        self.stdin.write_all(command.as_bytes()).unwrap();
         let result = self.thd_rx.recv().unwrap();
-        return Ok(result);
+        return Self::check_write(result);
     }   
 }
\ No newline at end of file
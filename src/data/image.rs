@@ -1,4 +1,5 @@
 use std::path::Path;
+use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 use eframe::egui::{Ui, ColorImage, TextureHandle};
 use egui::emath::TSTransform;
@@ -7,9 +8,15 @@ use image::codecs::gif::GifDecoder;
 use image::codecs::webp::WebPDecoder;
 use image::DynamicImage;
 use image::AnimationDecoder;
+use image::ImageDecoder;
+
+use crate::data::Property;
 
 /////////////////////////
 
+// the decoded frames in color next to the frames in the tone they are shown in
+type Frames = Result<(Arc<Vec<FrameData>>, Vec<FrameData>), String>;
+
 #[derive(PartialEq)]
 pub enum Status 
 {
@@ -29,6 +36,20 @@ pub struct Index
     pub image: usize, 
 }
 
+// how colors are shown in the image view, baked into the texture when it is uploaded
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+pub enum Tone
+{
+    #[default]
+    Color,
+    Grayscale,
+    Values(u8),
+}
+
+#[derive(Clone)]
 pub struct FrameData {
     pub image: ColorImage,
     pub delay: u32,
@@ -47,6 +68,19 @@ pub struct Image
     pub links: Vec<String>, 
     pub tags: Vec<String>,
     pub notes: String,
+    pub saved_notes: String,
+
+    // properties that can be searched
+    pub width: u32,
+    pub height: u32,
+    pub file_size: u64,
+    pub modified: String,
+    pub animated: bool,
+
+    // metadata writes
+    pub pending_writes: usize,
+    pub failed_writes: Vec<Property>,
+    pub write_error: Option<String>,
 
     // thumbnail
     pub thumb_texture: Option<TextureHandle>,
@@ -56,7 +90,9 @@ pub struct Image
     // full view
     pub full_texture: Vec<TextureData>,
     pub transform: Option<TSTransform>,
-    full_thread: Option<JoinHandle<Result<Vec<FrameData>, String>>>,
+    pub full_tone: Tone,
+    full_frames: Arc<Vec<FrameData>>, // decoded colors, kept so a new tone doesn't need the file again
+    full_thread: Option<JoinHandle<Frames>>,
     full_state: Status,
 }
 
@@ -79,19 +115,41 @@ impl Image
         size: size, 
         links: links, 
         tags: tags,
+        saved_notes: notes.clone(),
         notes:notes,
 
+        width: 0,
+        height: 0,
+        file_size: 0,
+        modified: String::new(),
+        animated: false,
+
+        pending_writes: 0,
+        failed_writes: Vec::new(),
+        write_error: None,
+
         thumb_texture: None,
         thumb_thread: None,
         thumb_state: Status::Unloaded,
 
         full_texture: Vec::new(),
         transform: None, 
+        full_tone: Tone::Color,
+        full_frames: Arc::new(Vec::new()),
         full_thread: None,
         full_state: Status::Unloaded,
         }
     }
 
+    // decodes a still image and turns it the way its exif orientation says
+    fn decode_oriented(mut decoder: impl ImageDecoder) -> image::ImageResult<DynamicImage>
+    {
+        let orientation = decoder.orientation().unwrap_or(image::metadata::Orientation::NoTransforms);
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        return Ok(image);
+    }
+
     fn create_thr_thumb(path: String) -> JoinHandle<Result<ColorImage, String>>
     {
         thread::spawn(move || -> Result<ColorImage, String>
@@ -102,7 +160,7 @@ impl Image
                 Err(_x) => return Err(format!("{} does not exist.", path)),
             };
 
-            let decoded = match input.decode()
+            let decoded = match input.into_decoder().and_then(Self::decode_oriented)
             {
                 Ok(x) => x,
                 Err(_x) => return Err(format!("Error when decoding {}.", path)),
@@ -123,9 +181,10 @@ impl Image
         })
     }
 
-    fn create_thr_full(path: String) -> JoinHandle<Result<Vec<FrameData>, String>>
+    // decodes every frame and returns them in color and in the given tone
+    fn create_thr_full(path: String, tone: Tone) -> JoinHandle<Frames>
     {
-        thread::spawn(move || -> Result<Vec<FrameData>, String>
+        thread::spawn(move || -> Frames
         {
             let file = match  image::ImageReader::open(path.clone())
             {
@@ -150,7 +209,11 @@ impl Image
                     // into_frames doesn't work for webp images, only webp animations.
                     if !decoder.has_animation()
                     {
-                        let frame = DynamicImage::from_decoder(decoder).unwrap().to_rgba8();
+                        let frame = match Self::decode_oriented(decoder)
+                        {
+                            Ok(x) => x.to_rgba8(),
+                            Err(x) => return Err(format!("webp error: {}.", x)),
+                        };
                         let size = [frame.width() as _, frame.height() as _];
                         let img = egui::ColorImage::from_rgba_unmultiplied(size, &frame);
                         images.push(FrameData{image: img, delay: 0});
@@ -200,7 +263,7 @@ impl Image
                 },
                 _ => 
                 {
-                    match file.decode()
+                    match file.into_decoder().and_then(Self::decode_oriented)
                     {
                         Ok(x) =>
                         {
@@ -215,7 +278,15 @@ impl Image
                 }, 
             }
 
-            Ok(images)
+            Ok(Self::tone_frames(Arc::new(images), tone))
+        })
+    }
+
+    fn create_thr_tone(frames: Arc<Vec<FrameData>>, tone: Tone) -> JoinHandle<Frames>
+    {
+        thread::spawn(move || -> Frames
+        {
+            Ok(Self::tone_frames(frames, tone))
         })
     }
 
@@ -233,20 +304,34 @@ impl Image
         return true
     }
 
-    pub fn load_full(&mut self) -> bool
+    pub fn load_full(&mut self, tone: Tone) -> bool
     {
         if self.full_state == Status::Loading {println!("tried to load image twice");}
 
-        self.full_thread = Some(Self::create_thr_full(self.file.clone()));
+        self.full_thread = Some(Self::create_thr_full(self.file.clone(), tone));
+        self.full_tone = tone;
         self.full_state = Status::Loading;
         return true
     }
 
+    // makes new textures in another tone from the frames that were already decoded
+    pub fn retone_full(&mut self, tone: Tone) -> bool
+    {
+        if self.full_state != Status::Loaded {return false;}
+        if self.full_frames.len() == 0 {return self.load_full(tone);}
+
+        self.full_thread = Some(Self::create_thr_tone(self.full_frames.clone(), tone));
+        self.full_tone = tone;
+        self.full_state = Status::Loading;
+        return true;
+    }
+
     pub fn clear_full(&mut self) -> bool
     {
         if self.full_state != Status::Loaded {return false;}
 
         self.full_texture = Vec::new();
+        self.full_frames = Arc::new(Vec::new());
         self.full_state = Status::Unloaded;
         return true;
     }
@@ -291,6 +376,35 @@ impl Image
         self.thumb_state = Status::Loaded;
     }
 
+    // runs in the loading threads, the per pixel work is too slow for the ui thread on large images
+    fn tone_frames(frames: Arc<Vec<FrameData>>, tone: Tone) -> (Arc<Vec<FrameData>>, Vec<FrameData>)
+    {
+        let mut toned = frames.as_ref().clone();
+        for frame in toned.iter_mut() {Self::apply_tone(&mut frame.image, tone);}
+        return (frames, toned);
+    }
+
+    fn apply_tone(image: &mut ColorImage, tone: Tone) -> ()
+    {
+        if tone == Tone::Color {return;}
+
+        for pixel in image.pixels.iter_mut()
+        {
+            let [r, g, b, a] = pixel.to_srgba_unmultiplied();
+            let mut value = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+
+            // posterize into evenly spaced values, from black to white
+            if let Tone::Values(levels) = tone
+            {
+                let steps = (levels.max(2) - 1) as f32;
+                value = (value / 255.0 * steps).round() / steps * 255.0;
+            }
+
+            let value = value.round() as u8;
+            *pixel = egui::Color32::from_rgba_unmultiplied(value, value, value, a);
+        }
+    }
+
     pub fn poll_full(&mut self, ui: &mut Ui) -> ()
     {
         if self.full_thread.as_ref().is_none()
@@ -313,7 +427,7 @@ impl Image
             }
         };
 
-        let result = match thread_result
+        let (frames, result) = match thread_result
         {
             Ok(x) => x,
             Err(x) => 
@@ -341,6 +455,7 @@ impl Image
         }
 
         self.full_texture = buffer;
+        self.full_frames = frames;
         self.full_state = Status::Loaded;
     }
 
@@ -358,13 +473,19 @@ impl Image
         return &self.full_state;
     }
 
-    pub fn add_tag(&mut self, tag: &String) -> ()
+    pub fn extension(&self) -> String
     {
-        if !self.tags.contains(&tag.to_lowercase())
-        {
-            self.tags.push(tag.to_lowercase().clone());
-            self.tags.sort();
-        }
+        let ext = Path::new(&self.file).extension().unwrap_or_default();
+        return ext.to_string_lossy().to_lowercase();
+    }
+
+    pub fn add_tag(&mut self, tag: &String) -> bool
+    {
+        if self.tags.contains(&tag.to_lowercase()) {return false;}
+
+        self.tags.push(tag.to_lowercase().clone());
+        self.tags.sort();
+        return true
     }
 
     pub fn remove_tag(&mut self, tag: &String) -> bool
@@ -378,14 +499,13 @@ impl Image
         return true
     }  
 
-    pub fn add_link(&mut self, link: &String) -> ()
+    pub fn add_link(&mut self, link: &String) -> bool
     {
-        println!("{}", link);
-        if !self.links.contains(&link)
-        {
-            self.links.push(link.clone());
-            self.links.sort();
-        }
+        if self.links.contains(&link) {return false;}
+
+        self.links.push(link.clone());
+        self.links.sort();
+        return true
     }
 
     pub fn remove_link(&mut self, link: &String) -> bool
@@ -399,14 +519,13 @@ impl Image
         return true
     }  
 
-    pub fn add_artist(&mut self, artist: &String) -> ()
+    pub fn add_artist(&mut self, artist: &String) -> bool
     {
-        println!("{}", artist);
-        if !self.artists.contains(&artist)
-        {
-            self.artists.push(artist.clone());
-            self.artists.sort();
-        }
+        if self.artists.contains(&artist) {return false;}
+
+        self.artists.push(artist.clone());
+        self.artists.sort();
+        return true
     }
 
     pub fn remove_artist(&mut self, artist: &String) -> bool
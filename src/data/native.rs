@@ -0,0 +1,811 @@
+use std::path::Path;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use serde_json::{Value, Map};
+
+use crate::data::{Data, Backend};
+use crate::data::tiff::{self, Exif};
+
+/////////////////////////
+
+// reads and writes the same exif fields as exiftool, without needing exiftool.
+// supports jpeg, png, tiff and webp. other formats are read without metadata.
+pub struct Native {}
+
+enum Format
+{
+    Jpeg,
+    Png,
+    Tiff,
+    Webp,
+    Other,
+}
+
+/////////////////////////
+
+fn detect(data: &[u8]) -> Format
+{
+    if data.starts_with(&[0xFF, 0xD8]) {return Format::Jpeg;}
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {return Format::Png;}
+    if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {return Format::Tiff;}
+    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {return Format::Webp;}
+    return Format::Other;
+}
+
+fn crc32(data: &[u8]) -> u32
+{
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in data
+    {
+        crc ^= *byte as u32;
+        for _ in 0..8
+        {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    return !crc;
+}
+
+//////////
+// jpeg //
+//////////
+
+// returns the (marker, start, end) of every segment before the scan data, and where the scan starts
+fn jpeg_segments(data: &[u8]) -> Result<(Vec<(u8, usize, usize)>, usize), String>
+{
+    let mut segments = Vec::new();
+    let mut pos = 2;
+
+    loop
+    {
+        if pos + 1 >= data.len() || data[pos] != 0xFF {return Err("broken jpeg structure".to_string());}
+        let marker = data[pos + 1];
+
+        match marker
+        {
+            0xFF => {pos += 1; continue;},
+            0xDA | 0xD9 => return Ok((segments, pos)),
+            0x01 | 0xD0..=0xD7 =>
+            {
+                segments.push((marker, pos, pos + 2));
+                pos += 2;
+            },
+            _ =>
+            {
+                let Some(len) = data.get(pos+2..pos+4) else { return Err("broken jpeg structure".to_string()) };
+                let end = pos + 2 + u16::from_be_bytes([len[0], len[1]]) as usize;
+                if end > data.len() {return Err("broken jpeg structure".to_string());}
+                segments.push((marker, pos, end));
+                pos = end;
+            },
+        }
+    }
+}
+
+fn is_exif_segment(data: &[u8], segment: &(u8, usize, usize)) -> bool
+{
+    return segment.0 == 0xE1 && data[segment.1..segment.2].get(4..10) == Some(b"Exif\0\0");
+}
+
+fn jpeg_read(data: &[u8]) -> Result<Option<Exif>, String>
+{
+    let (segments, _) = jpeg_segments(data)?;
+    for segment in &segments
+    {
+        if is_exif_segment(data, segment) {return Ok(Exif::parse(&data[segment.1+10..segment.2]));}
+    }
+    return Ok(None);
+}
+
+fn jpeg_write(data: &[u8], exif: &Exif) -> Result<Vec<u8>, String>
+{
+    let (segments, scan) = jpeg_segments(data)?;
+    let tiff = exif.to_bytes();
+    if tiff.len() + 8 > 0xFFFF {return Err("exif data too large for a jpeg segment".to_string());}
+
+    let mut app1 = vec![0xFF, 0xE1];
+    app1.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+    app1.extend_from_slice(b"Exif\0\0");
+    app1.extend_from_slice(&tiff);
+
+    let mut out = vec![0xFF, 0xD8];
+    let mut written = false;
+
+    for segment in &segments
+    {
+        if is_exif_segment(data, segment)
+        {
+            if !written {out.extend_from_slice(&app1); written = true;}
+            continue;
+        }
+
+        // exif goes right after the jfif header when there is one
+        if !written && segment.0 != 0xE0
+        {
+            out.extend_from_slice(&app1);
+            written = true;
+        }
+        out.extend_from_slice(&data[segment.1..segment.2]);
+    }
+
+    if !written {out.extend_from_slice(&app1);}
+    out.extend_from_slice(&data[scan..]);
+    return Ok(out);
+}
+
+/////////
+// png //
+/////////
+
+fn png_chunks(data: &[u8]) -> Result<Vec<([u8; 4], usize, usize)>, String>
+{
+    let mut chunks = Vec::new();
+    let mut pos = 8;
+
+    while pos + 8 <= data.len()
+    {
+        let len = u32::from_be_bytes(data[pos..pos+4].try_into().unwrap()) as usize;
+        let kind: [u8; 4] = data[pos+4..pos+8].try_into().unwrap();
+        let end = pos + 12 + len;
+        if end > data.len() {return Err("broken png structure".to_string());}
+
+        chunks.push((kind, pos, end));
+        pos = end;
+        if &kind == b"IEND" {break;}
+    }
+
+    return Ok(chunks);
+}
+
+fn png_read(data: &[u8]) -> Result<Option<Exif>, String>
+{
+    for (kind, start, end) in png_chunks(data)?
+    {
+        if &kind == b"eXIf" {return Ok(Exif::parse(&data[start+8..end-4]));}
+    }
+    return Ok(None);
+}
+
+fn png_write(data: &[u8], exif: &Exif) -> Result<Vec<u8>, String>
+{
+    let tiff = exif.to_bytes();
+    let mut chunk = (tiff.len() as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(b"eXIf");
+    chunk.extend_from_slice(&tiff);
+    let crc = crc32(&chunk[4..]);
+    chunk.extend_from_slice(&crc.to_be_bytes());
+
+    let mut out = data[0..8].to_vec();
+    let mut written = false;
+
+    for (kind, start, end) in png_chunks(data)?
+    {
+        if &kind == b"eXIf" {continue;}
+        if !written && (&kind == b"IDAT" || &kind == b"IEND")
+        {
+            out.extend_from_slice(&chunk);
+            written = true;
+        }
+        out.extend_from_slice(&data[start..end]);
+    }
+
+    if !written {return Err("png has no image data".to_string());}
+    return Ok(out);
+}
+
+//////////
+// webp //
+//////////
+
+fn webp_chunks(data: &[u8]) -> Result<Vec<([u8; 4], Vec<u8>)>, String>
+{
+    let mut chunks = Vec::new();
+    let mut pos = 12;
+
+    while pos + 8 <= data.len()
+    {
+        let kind: [u8; 4] = data[pos..pos+4].try_into().unwrap();
+        let len = u32::from_le_bytes(data[pos+4..pos+8].try_into().unwrap()) as usize;
+        let end = pos + 8 + len;
+        if end > data.len() {return Err("broken webp structure".to_string());}
+
+        chunks.push((kind, data[pos+8..end].to_vec()));
+        pos = end + (len % 2);
+    }
+
+    return Ok(chunks);
+}
+
+fn webp_exif(chunk: &Vec<u8>) -> Option<Exif>
+{
+    // some writers keep the jpeg style header in front of the tiff data
+    if chunk.starts_with(b"Exif\0\0") {return Exif::parse(&chunk[6..]);}
+    return Exif::parse(chunk);
+}
+
+fn webp_read(data: &[u8]) -> Result<Option<Exif>, String>
+{
+    for (kind, chunk) in webp_chunks(data)?
+    {
+        if &kind == b"EXIF" {return Ok(webp_exif(&chunk));}
+    }
+    return Ok(None);
+}
+
+// the extended header a simple (lossy or lossless) webp needs before it can hold exif
+fn webp_vp8x(chunks: &Vec<([u8; 4], Vec<u8>)>, path: &Path) -> Result<Vec<u8>, String>
+{
+    let (width, height) = match image::image_dimensions(path)
+    {
+        Ok(x) => x,
+        Err(x) => return Err(format!("webp error: {}", x)),
+    };
+
+    let mut flags = 0u8;
+    for (kind, chunk) in chunks
+    {
+        // lossless images store whether alpha is used in their header
+        if kind == b"VP8L" && chunk.len() >= 5 && (chunk[4] & 0x10) != 0 {flags |= 0x10;}
+    }
+
+    let mut vp8x = vec![flags, 0, 0, 0];
+    vp8x.extend_from_slice(&(width - 1).to_le_bytes()[0..3]);
+    vp8x.extend_from_slice(&(height - 1).to_le_bytes()[0..3]);
+    return Ok(vp8x);
+}
+
+fn webp_write(data: &[u8], exif: &Exif, path: &Path) -> Result<Vec<u8>, String>
+{
+    let mut chunks = webp_chunks(data)?;
+    chunks.retain(|x| &x.0 != b"EXIF");
+
+    if chunks.first().map(|x| &x.0) != Some(b"VP8X")
+    {
+        let vp8x = webp_vp8x(&chunks, path)?;
+        chunks.insert(0, (*b"VP8X", vp8x));
+    }
+    chunks[0].1[0] |= 0x08;
+
+    // exif comes after the image data, but before xmp
+    let pos = chunks.iter().position(|x| &x.0 == b"XMP ").unwrap_or(chunks.len());
+    chunks.insert(pos, (*b"EXIF", exif.to_bytes()));
+
+    let mut body = b"WEBP".to_vec();
+    for (kind, chunk) in &chunks
+    {
+        body.extend_from_slice(kind);
+        body.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        body.extend_from_slice(chunk);
+        if chunk.len() % 2 == 1 {body.push(0);}
+    }
+
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(&body);
+    return Ok(out);
+}
+
+fn gif_frames(data: &[u8]) -> u64
+{
+    let skip_blocks = |mut pos: usize| -> usize {
+        while let Some(&len) = data.get(pos)
+        {
+            pos += 1 + len as usize;
+            if len == 0 {break;}
+        }
+        return pos;
+    };
+
+    if data.len() < 13 {return 0;}
+    let mut pos = 13;
+    if data[10] & 0x80 != 0 {pos += 3 << ((data[10] & 7) + 1);}
+
+    let mut frames = 0;
+    loop
+    {
+        match data.get(pos)
+        {
+            Some(0x21) => pos = skip_blocks(pos + 2),
+            Some(0x2C) =>
+            {
+                frames += 1;
+                let Some(&flags) = data.get(pos + 9) else { break };
+                pos += 10;
+                if flags & 0x80 != 0 {pos += 3 << ((flags & 7) + 1);}
+                pos = skip_blocks(pos + 1);
+            },
+            _ => break,
+        }
+    }
+    return frames;
+}
+
+// the size as written in the headers, so the image doesn't have to be opened again
+fn dimensions(data: &[u8]) -> Option<(u32, u32)>
+{
+    let be = |x: &[u8]| u16::from_be_bytes([x[0], x[1]]) as u32;
+    let le24 = |x: &[u8]| x[0] as u32 | (x[1] as u32) << 8 | (x[2] as u32) << 16;
+
+    if data.starts_with(b"GIF8") && data.len() >= 10
+    {
+        return Some((u16::from_le_bytes([data[6], data[7]]) as u32, u16::from_le_bytes([data[8], data[9]]) as u32));
+    }
+
+    match detect(data)
+    {
+        Format::Jpeg =>
+        {
+            // the start of frame segments, c4, c8 and cc are other segments in the same range
+            let (segments, _) = jpeg_segments(data).ok()?;
+            let (_, start, end) = segments.iter().find(|x| matches!(x.0, 0xC0..=0xCF) && ![0xC4, 0xC8, 0xCC].contains(&x.0))?;
+            if end - start < 9 {return None;}
+            return Some((be(&data[start+7..]), be(&data[start+5..])));
+        },
+        Format::Png =>
+        {
+            if data.len() < 24 || &data[12..16] != b"IHDR" {return None;}
+            let width = u32::from_be_bytes(data[16..20].try_into().ok()?);
+            let height = u32::from_be_bytes(data[20..24].try_into().ok()?);
+            return Some((width, height));
+        },
+        Format::Webp =>
+        {
+            // only the extended format has the size in its own chunk, the others keep it in the image data
+            let (kind, chunk) = webp_chunks(data).ok()?.into_iter().next()?;
+            if &kind != b"VP8X" || chunk.len() < 10 {return None;}
+            return Some((le24(&chunk[4..7]) + 1, le24(&chunk[7..10]) + 1));
+        },
+        _ => return None,
+    }
+}
+
+// reads on until the data is end bytes long, returns false when the file ends before that
+fn read_to(file: &mut File, data: &mut Vec<u8>, end: usize) -> std::io::Result<bool>
+{
+    if data.len() >= end {return Ok(true);}
+    let len = (end - data.len()) as u64;
+    file.by_ref().take(len).read_to_end(data)?;
+    return Ok(data.len() == end);
+}
+
+// reads everything that holds metadata and leaves out the image data where the format allows it.
+// jpeg stops at the scan, png at the image data, and webp skips over its image chunks.
+// gif has to be read whole to count the frames, and tiff can keep its metadata anywhere
+fn read_header(path: &String) -> std::io::Result<Vec<u8>>
+{
+    let mut file = File::open(path)?;
+    let mut data = Vec::new();
+    read_to(&mut file, &mut data, 12)?;
+
+    if data.starts_with(b"GIF8")
+    {
+        file.read_to_end(&mut data)?;
+        return Ok(data);
+    }
+
+    match detect(&data)
+    {
+        Format::Jpeg =>
+        {
+            let mut pos = 2;
+            loop
+            {
+                if !read_to(&mut file, &mut data, pos + 4)? {break;}
+                if data[pos] != 0xFF {break;}
+
+                match data[pos + 1]
+                {
+                    0xFF => pos += 1,
+                    0xDA | 0xD9 => break,
+                    0x01 | 0xD0..=0xD7 => pos += 2,
+                    _ =>
+                    {
+                        let end = pos + 2 + u16::from_be_bytes([data[pos+2], data[pos+3]]) as usize;
+                        if !read_to(&mut file, &mut data, end)? {break;}
+                        pos = end;
+                    },
+                }
+            }
+        },
+        Format::Png =>
+        {
+            let mut pos = 8;
+            loop
+            {
+                if !read_to(&mut file, &mut data, pos + 8)? {break;}
+                let len = u32::from_be_bytes(data[pos..pos+4].try_into().unwrap()) as usize;
+
+                // the metadata chunks come before the image data
+                if &data[pos+4..pos+8] == b"IDAT" || &data[pos+4..pos+8] == b"IEND"
+                {
+                    data.truncate(pos);
+                    break;
+                }
+                if !read_to(&mut file, &mut data, pos + 12 + len)? {break;}
+                pos += 12 + len;
+            }
+        },
+        Format::Webp =>
+        {
+            let mut pos = 12;
+            loop
+            {
+                if !read_to(&mut file, &mut data, pos + 8)? {data.truncate(pos); break;}
+                let kind: [u8; 4] = data[pos..pos+4].try_into().unwrap();
+                let len = u32::from_le_bytes(data[pos+4..pos+8].try_into().unwrap()) as usize;
+                let padded = len + (len % 2);
+
+                // the exif chunk usually comes after the image data, so jump over it
+                if [*b"VP8 ", *b"VP8L", *b"ALPH", *b"ANMF"].contains(&kind)
+                {
+                    data.truncate(pos);
+                    file.seek(SeekFrom::Current(padded as i64))?;
+                    continue;
+                }
+                if !read_to(&mut file, &mut data, pos + 8 + padded)? {break;}
+                pos += 8 + padded;
+            }
+        },
+        Format::Tiff => {file.read_to_end(&mut data)?;},
+        Format::Other => (),
+    }
+    return Ok(data);
+}
+
+// the same fields exiftool gives for animations
+fn animation_info(data: &[u8]) -> Option<(&'static str, Value)>
+{
+    if data.starts_with(b"GIF8") {return Some(("FrameCount", Value::from(gif_frames(data))));}
+
+    match detect(data)
+    {
+        Format::Png =>
+        {
+            for (kind, start, end) in png_chunks(data).ok()?
+            {
+                if &kind != b"acTL" || end - start < 16 {continue;}
+                let frames = u32::from_be_bytes(data[start+8..start+12].try_into().ok()?);
+                return Some(("AnimationFrames", Value::from(frames)));
+            }
+            return None;
+        },
+        Format::Webp =>
+        {
+            for (kind, chunk) in webp_chunks(data).ok()?
+            {
+                if &kind == b"VP8X" && chunk.first()? & 0x02 != 0 {return Some(("WebP_Flags", Value::from("Animation")));}
+            }
+            return None;
+        },
+        _ => return None,
+    }
+}
+
+/////////////////////////
+
+impl Native
+{
+    pub fn new() -> Native
+    {
+        return Native{};
+    }
+
+    fn read_exif(data: &[u8]) -> Result<Option<Exif>, String>
+    {
+        match detect(data)
+        {
+            Format::Jpeg => return jpeg_read(data),
+            Format::Png => return png_read(data),
+            Format::Tiff => return Ok(Exif::parse(data)),
+            Format::Webp => return webp_read(data),
+            Format::Other => return Ok(None),
+        }
+    }
+
+    fn image_info(path: &String) -> Value
+    {
+        let mut info = Map::new();
+        info.insert("SourceFile".to_string(), Value::from(path.clone()));
+
+        let data = read_header(path).unwrap_or_default();
+        if let Some((w, h)) = dimensions(&data).or_else(|| image::image_dimensions(path).ok())
+        {
+            info.insert("ImageSize".to_string(), Value::from(format!("{}x{}", w, h)));
+            info.insert("ImageWidth".to_string(), Value::from(w));
+            info.insert("ImageHeight".to_string(), Value::from(h));
+        }
+
+        if data.len() == 0 {return Value::Object(info);}
+        if let Some((name, value)) = animation_info(&data) {info.insert(name.to_string(), value);}
+
+        let exif = Self::read_exif(&data).unwrap_or(None);
+
+        let Some(exif) = exif else { return Value::Object(info) };
+        let fields = [("Artist", tiff::TAG_ARTIST),
+                      ("PageName", tiff::TAG_PAGE_NAME),
+                      ("ImageDescription", tiff::TAG_DESCRIPTION),
+                      ("UserComment", tiff::TAG_USER_COMMENT)];
+
+        for (name, tag) in fields
+        {
+            if let Some(value) = exif.get_string(tag)
+            {
+                info.insert(name.to_string(), Value::from(value));
+            }
+        }
+        if let Some(x) = exif.get_short(tiff::TAG_ORIENTATION) {info.insert("Orientation".to_string(), Value::from(x));}
+
+        return Value::Object(info);
+    }
+
+    fn write_field(&mut self, path: &String, tag: u16, value: &String) -> Result<String, String>
+    {
+        let data = match std::fs::read(path)
+        {
+            Ok(x) => x,
+            Err(x) => return Err(format!("could not read {}: {}", path, x)),
+        };
+
+        let format = detect(&data);
+        let original = match Self::read_exif(&data)?
+        {
+            Some(x) => x,
+            None => match format
+            {
+                Format::Tiff => return Err(format!("unsupported tiff structure in {}", path)),
+                _ => Exif::empty(false),
+            },
+        };
+
+        let mut exif = original.clone();
+        exif.set_string(tag, value);
+
+        let output = match format
+        {
+            Format::Jpeg => jpeg_write(&data, &exif)?,
+            Format::Png => png_write(&data, &exif)?,
+            Format::Webp => webp_write(&data, &exif, Path::new(path))?,
+            Format::Tiff =>
+            {
+                let mut file = data.clone();
+                exif.append_to(&mut file, &original);
+                file
+            },
+            Format::Other => return Err(format!("writing metadata is not supported for {}", path)),
+        };
+
+        // write next to the original and swap, so a failed write never leaves a broken image
+        let tmp = format!("{}.ref_viewer_tmp", path);
+        if let Err(x) = std::fs::write(&tmp, &output)
+        {
+            return Err(format!("could not write {}: {}", path, x));
+        }
+
+        if let Ok(meta) = std::fs::metadata(path) {let _ = std::fs::set_permissions(&tmp, meta.permissions());}
+        if let Err(x) = std::fs::rename(&tmp, path)
+        {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(format!("could not replace {}: {}", path, x));
+        }
+
+        return Ok("    1 image files updated\n".to_string());
+    }
+}
+
+impl Backend for Native
+{
+    fn get_folder_data(&mut self, path: &String) ->  Result<String, String>
+    {
+        let mut files = Data::list_images(Path::new(path));
+        files.sort();
+        return self.get_files_data(&files);
+    }
+
+    fn get_files_data(&mut self, paths: &Vec<String>) ->  Result<String, String>
+    {
+        let infos: Vec<Value> = paths.iter().map(|x| Self::image_info(x)).collect();
+        if infos.len() == 0 {return Ok("".to_string());}
+        return Ok(Value::Array(infos).to_string());
+    }
+
+    fn set_notes(&mut self, path: &String, notes: &String) ->  Result<String, String>
+    {
+        // exiftool gets the notes with escaped quotes, but stores them without the escapes
+        let notes = notes.replace("\\\"", "\"");
+        return self.write_field(path, tiff::TAG_USER_COMMENT, &notes);
+    }
+
+    fn set_tags(&mut self, path: &String, tag: &String) ->  Result<String, String>
+    {
+        return self.write_field(path, tiff::TAG_DESCRIPTION, tag);
+    }
+
+    fn set_link(&mut self, path: &String, tag: &String) ->  Result<String, String>
+    {
+        return self.write_field(path, tiff::TAG_PAGE_NAME, tag);
+    }
+
+    fn set_artist(&mut self, path: &String, tag: &String) ->  Result<String, String>
+    {
+        return self.write_field(path, tiff::TAG_ARTIST, tag);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const TAGS: &str = "[\"hand\",\"pose:standing\"]";
+    const NOTES: &str = "notes with \"quotes\" and ünïcode";
+
+    fn temp_file(name: &str) -> String
+    {
+        let file = format!("ref_viewer_test_{}_{}", std::process::id(), name);
+        return std::env::temp_dir().join(file).to_string_lossy().into_owned();
+    }
+
+    fn write_fields(path: &String) -> ()
+    {
+        let mut native = Native::new();
+        native.set_tags(path, &TAGS.to_string()).unwrap();
+        native.set_artist(path, &"[\"someone\"]".to_string()).unwrap();
+        native.set_link(path, &"[\"https://example.com/a\"]".to_string()).unwrap();
+        native.set_notes(path, &NOTES.replace("\"", "\\\"")).unwrap();
+    }
+
+    fn check_fields(path: &String) -> Value
+    {
+        let info = Native::image_info(path);
+        assert_eq!(info["ImageDescription"], TAGS);
+        assert_eq!(info["Artist"], "[\"someone\"]");
+        assert_eq!(info["PageName"], "[\"https://example.com/a\"]");
+        assert_eq!(info["UserComment"], NOTES);
+        return info;
+    }
+
+    // writes every field twice, the second time the metadata block already exists and gets replaced
+    fn round_trip(ext: &str) -> ()
+    {
+        let path = temp_file(&format!("round_trip.{}", ext));
+        let pixels = image::RgbImage::from_fn(16, 8, |x, y| image::Rgb([(x * 16) as u8, (y * 32) as u8, 128]));
+        pixels.save(&path).unwrap();
+
+        write_fields(&path);
+        check_fields(&path);
+        write_fields(&path);
+        let info = check_fields(&path);
+        assert_eq!(info["ImageWidth"], 16);
+
+        let decoded = image::open(&path).expect("image no longer decodes");
+        assert_eq!((decoded.width(), decoded.height()), (16, 8));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn round_trip_jpeg()
+    {
+        round_trip("jpg");
+    }
+
+    #[test]
+    fn round_trip_png()
+    {
+        round_trip("png");
+    }
+
+    #[test]
+    fn round_trip_webp()
+    {
+        round_trip("webp");
+    }
+
+    #[test]
+    fn round_trip_tiff()
+    {
+        round_trip("tiff");
+    }
+
+    // the headers have to hold the metadata and the size, without the pixels that come after them
+    fn header_only(ext: &str) -> ()
+    {
+        let path = temp_file(&format!("header.{}", ext));
+        let pixels = image::RgbImage::from_fn(300, 200, |x, y| image::Rgb([(x * y) as u8, (x ^ y) as u8, (x + y) as u8]));
+        pixels.save(&path).unwrap();
+        write_fields(&path);
+
+        let header = read_header(&path).unwrap();
+        assert!(header.len() < std::fs::metadata(&path).unwrap().len() as usize);
+        assert_eq!(dimensions(&header), Some((300, 200)));
+
+        let info = check_fields(&path);
+        assert_eq!(info["ImageSize"], "300x200");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn header_only_jpeg()
+    {
+        header_only("jpg");
+    }
+
+    #[test]
+    fn header_only_png()
+    {
+        header_only("png");
+    }
+
+    #[test]
+    fn header_only_webp()
+    {
+        header_only("webp");
+    }
+
+    // a 2x2 rgb tiff with an orientation and an exif ifd that holds the exif version
+    fn tiff_with_exif() -> Vec<u8>
+    {
+        let mut data = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+        let entry = |data: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: [u8; 4]| {
+            data.extend_from_slice(&tag.to_le_bytes());
+            data.extend_from_slice(&kind.to_le_bytes());
+            data.extend_from_slice(&count.to_le_bytes());
+            data.extend_from_slice(&value);
+        };
+        let short = |x: u16| {let b = x.to_le_bytes(); [b[0], b[1], 0, 0]};
+        let long = |x: u32| x.to_le_bytes();
+
+        // ifd0 at 8 with 12 entries ends at 158, then bits per sample, the exif ifd and the pixels
+        let (bits, exif, pixels) = (158, 164, 182);
+        data.extend_from_slice(&12u16.to_le_bytes());
+        entry(&mut data, 256, 3, 1, short(2));
+        entry(&mut data, 257, 3, 1, short(2));
+        entry(&mut data, 258, 3, 3, long(bits));
+        entry(&mut data, 259, 3, 1, short(1));
+        entry(&mut data, 262, 3, 1, short(2));
+        entry(&mut data, 273, 4, 1, long(pixels));
+        entry(&mut data, 274, 3, 1, short(6));
+        entry(&mut data, 277, 3, 1, short(3));
+        entry(&mut data, 278, 3, 1, short(2));
+        entry(&mut data, 279, 4, 1, long(12));
+        entry(&mut data, 284, 3, 1, short(1));
+        entry(&mut data, 0x8769, 4, 1, long(exif));
+        data.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(data.len(), bits as usize);
+
+        for _ in 0..3 {data.extend_from_slice(&8u16.to_le_bytes());}
+        assert_eq!(data.len(), exif as usize);
+
+        data.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut data, 0x9000, 7, 4, *b"0230");
+        data.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(data.len(), pixels as usize);
+
+        data.extend_from_slice(&[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255]);
+        return data;
+    }
+
+    #[test]
+    fn tiff_with_exif_ifd()
+    {
+        let path = temp_file("exif_ifd.tiff");
+        std::fs::write(&path, tiff_with_exif()).unwrap();
+        assert_eq!(Native::image_info(&path)["Orientation"], 6);
+
+        write_fields(&path);
+        write_fields(&path);
+        let info = check_fields(&path);
+        assert_eq!(info["Orientation"], 6);
+
+        // entries that were already in the exif ifd have to survive the rewrite
+        let data = std::fs::read(&path).unwrap();
+        let exif = Exif::parse(&data).unwrap();
+        let version = exif.exif.as_ref().and_then(|x| x.get(0x9000)).map(|x| x.data.clone());
+        assert_eq!(version, Some(b"0230".to_vec()));
+
+        let decoded = image::open(&path).expect("image no longer decodes").to_rgb8();
+        assert_eq!(decoded.dimensions(), (2, 2));
+        assert_eq!(decoded.get_pixel(1, 1).0, [255, 255, 255]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,547 @@
+/////////////////////////
+
+// search query grammar, from loosest to tightest binding:
+//   expr    = and ("|" and)*
+//   and     = unary unary*
+//   unary   = "-" unary | primary
+//   primary = "(" expr ")" | term
+//   term    = [field ":"] (word | "quoted text")
+
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub enum Field
+{
+    Any,
+    Tag,
+    Artist,
+    Source,
+    Notes,
+    Width,
+    Height,
+    Orientation,
+    Ext,
+    Animated,
+    FileSize,
+    Modified,
+}
+
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Copy)]
+pub enum Compare
+{
+    Less,
+    LessEq,
+    Equal,
+    GreaterEq,
+    Greater,
+}
+
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub enum Query
+{
+    All,
+    Term(Field, String),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+#[derive(PartialEq)]
+#[derive(Clone)]
+enum Token
+{
+    Word(String),
+    Quoted(String),
+    Open,
+    Close,
+    Or,
+    Not,
+}
+
+struct Parser
+{
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    len: usize,
+}
+
+/////////////////////////
+
+fn field_from_str(name: &str) -> Option<Field>
+{
+    match name
+    {
+        "tag" => Some(Field::Tag),
+        "artist" => Some(Field::Artist),
+        "source" => Some(Field::Source),
+        "notes" => Some(Field::Notes),
+        "width" => Some(Field::Width),
+        "height" => Some(Field::Height),
+        "orientation" => Some(Field::Orientation),
+        "ext" => Some(Field::Ext),
+        "animated" => Some(Field::Animated),
+        "filesize" => Some(Field::FileSize),
+        "modified" => Some(Field::Modified),
+        _ => None,
+    }
+}
+
+fn tokenize(input: &String) -> Result<Vec<(Token, usize)>, String>
+{
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len()
+    {
+        let c = chars[i];
+        match c
+        {
+            ' ' | '\t' | '\n' => {i += 1;},
+            '(' => {tokens.push((Token::Open, i)); i += 1;},
+            ')' => {tokens.push((Token::Close, i)); i += 1;},
+            '|' => {tokens.push((Token::Or, i)); i += 1;},
+            '-' => {tokens.push((Token::Not, i)); i += 1;},
+            '"' =>
+            {
+                let start = i;
+                let mut text = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {text.push(chars[i]); i += 1;}
+                if i == chars.len() {return Err(format!("missing closing quote for the one at {}", start + 1));}
+                tokens.push((Token::Quoted(text), start));
+                i += 1;
+            },
+            _ =>
+            {
+                let start = i;
+                let mut text = String::new();
+                while i < chars.len() && !" \t\n()|\"".contains(chars[i])
+                {
+                    text.push(chars[i]);
+                    i += 1;
+                }
+
+                // allow field:"quoted text" by gluing the quote onto the prefix
+                if text.ends_with(':') && i < chars.len() && chars[i] == '"'
+                {
+                    i += 1;
+                    while i < chars.len() && chars[i] != '"' {text.push(chars[i]); i += 1;}
+                    if i == chars.len() {return Err(format!("missing closing quote after '{}'", text));}
+                    i += 1;
+                    tokens.push((Token::Quoted(text), start));
+                }
+                else
+                {
+                    tokens.push((Token::Word(text), start));
+                }
+            },
+        }
+    }
+
+    return Ok(tokens);
+}
+
+pub fn is_field(name: &str) -> bool
+{
+    return field_from_str(name).is_some();
+}
+
+// quotes a tag or artist when the parser would otherwise split it up or read it as an operator.
+// quotes can't be escaped, so those get dropped from the term.
+pub fn quote_term(term: &str) -> String
+{
+    let special = term.starts_with('-') || term.contains(|c: char| c.is_whitespace() || "()|\"".contains(c));
+    if !special {return term.to_string();}
+    return format!("\"{}\"", term.replace('"', ""));
+}
+
+// splits a search into its top level terms as they were written, groups and quotes stay in one piece
+pub fn split_terms(search: &str) -> Vec<String>
+{
+    let mut terms = Vec::new();
+    let mut term = String::new();
+    let (mut depth, mut quoted) = (0, false);
+
+    for c in search.chars()
+    {
+        match c
+        {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted && depth > 0 => depth -= 1,
+            c if c.is_whitespace() && !quoted && depth == 0 =>
+            {
+                if term.len() > 0 {terms.push(std::mem::take(&mut term));}
+                continue;
+            },
+            _ => (),
+        }
+        term.push(c);
+    }
+
+    if term.len() > 0 {terms.push(term);}
+    return terms;
+}
+
+// catches values that can never match, so they show up as a search error
+fn check_property(field: &Field, prefix: &str, value: &str) -> Result<(), String>
+{
+    let (_, rest) = split_compare(value);
+    match field
+    {
+        Field::Width | Field::Height if rest.parse::<u32>().is_err() =>
+            return Err(format!("'{}:' needs a number, like {}:>2000", prefix, prefix)),
+        Field::FileSize if parse_filesize(rest).is_none() =>
+            return Err(format!("'{}:' needs a size, like {}:<2mb", prefix, prefix)),
+        Field::Orientation if !["landscape", "portrait", "square"].contains(&value) =>
+            return Err(format!("'{}:' can be landscape, portrait or square", prefix)),
+        Field::Animated if parse_bool(value).is_none() =>
+            return Err(format!("'{}:' can be yes or no", prefix)),
+        _ => return Ok(()),
+    }
+}
+
+fn make_term(text: &String, quoted: bool) -> Result<Query, String>
+{
+    if let Some((prefix, value)) = text.split_once(':')
+    {
+        if let Some(field) = field_from_str(&prefix.to_lowercase())
+        {
+            if value.len() == 0 {return Err(format!("'{}:' needs a value", prefix));}
+            check_property(&field, prefix, &value.to_lowercase())?;
+            return Ok(Query::Term(field, value.to_lowercase()));
+        }
+    }
+
+    if quoted && text.trim().len() == 0 {return Err("empty quotes".to_string());}
+    return Ok(Query::Term(Field::Any, text.clone()));
+}
+
+impl Parser
+{
+    fn peek(&self) -> Option<&Token>
+    {
+        return self.tokens.get(self.pos).map(|x| &x.0);
+    }
+
+    fn position(&self) -> usize
+    {
+        match self.tokens.get(self.pos)
+        {
+            Some(x) => return x.1 + 1,
+            None => return self.len + 1,
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Query, String>
+    {
+        let mut parts = vec![self.parse_and()?];
+
+        while self.peek() == Some(&Token::Or)
+        {
+            self.pos += 1;
+            if self.peek().is_none() || self.peek() == Some(&Token::Close) || self.peek() == Some(&Token::Or)
+            {
+                return Err(format!("'|' at {} has nothing on its right side", self.tokens[self.pos-1].1 + 1));
+            }
+            parts.push(self.parse_and()?);
+        }
+
+        if parts.len() == 1 {return Ok(parts.pop().unwrap());}
+        return Ok(Query::Or(parts));
+    }
+
+    fn parse_and(&mut self) -> Result<Query, String>
+    {
+        let mut parts = Vec::new();
+
+        loop
+        {
+            match self.peek()
+            {
+                None | Some(Token::Close) | Some(Token::Or) => break,
+                _ => parts.push(self.parse_unary()?),
+            }
+        }
+
+        match parts.len()
+        {
+            0 =>
+            {
+                match self.peek()
+                {
+                    Some(Token::Or) => return Err(format!("'|' at {} has nothing on its left side", self.position())),
+                    Some(Token::Close) => return Err(format!("unexpected ')' at {}", self.position())),
+                    _ => return Err("empty search".to_string()),
+                }
+            },
+            1 => return Ok(parts.pop().unwrap()),
+            _ => return Ok(Query::And(parts)),
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, String>
+    {
+        if self.peek() == Some(&Token::Not)
+        {
+            let start = self.position();
+            self.pos += 1;
+            match self.peek()
+            {
+                None | Some(Token::Close) | Some(Token::Or) =>
+                    return Err(format!("'-' at {} is not followed by a term", start)),
+                _ => return Ok(Query::Not(Box::new(self.parse_unary()?))),
+            }
+        }
+
+        return self.parse_primary();
+    }
+
+    fn parse_primary(&mut self) -> Result<Query, String>
+    {
+        let start = self.position();
+        let token = self.tokens[self.pos].0.clone();
+        self.pos += 1;
+
+        match token
+        {
+            Token::Open =>
+            {
+                if self.peek() == Some(&Token::Close) {return Err(format!("empty group at {}", start));}
+                let inner = self.parse_expr()?;
+                if self.peek() != Some(&Token::Close) {return Err(format!("missing ')' for the '(' at {}", start));}
+                self.pos += 1;
+                return Ok(inner);
+            },
+            Token::Word(x) => return make_term(&x, false),
+            Token::Quoted(x) => return make_term(&x, true),
+            _ => return Err(format!("unexpected symbol at {}", start)),
+        }
+    }
+}
+
+/////////////////////////
+
+// the terms of one field that results have to match, excluded terms are left out
+pub fn field_terms(query: &Query, field: &Field) -> Vec<String>
+{
+    match query
+    {
+        Query::Term(x, text) if x == field => return vec![text.clone()],
+        Query::And(parts) | Query::Or(parts) => return parts.iter().flat_map(|x| field_terms(x, field)).collect(),
+        _ => return Vec::new(),
+    }
+}
+
+// splits notes into lowercase words for the notes index
+pub fn words(text: &str) -> Vec<String>
+{
+    let mut words: Vec<String> = text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|x| x.len() > 0).map(|x| x.to_lowercase()).collect();
+    words.sort();
+    words.dedup();
+    return words;
+}
+
+// ">2000" -> (Greater, "2000"), without an operator the value has to be equal
+pub fn split_compare(value: &str) -> (Compare, &str)
+{
+    let operators = [(">=", Compare::GreaterEq), ("<=", Compare::LessEq), (">", Compare::Greater),
+                     ("<", Compare::Less), ("=", Compare::Equal)];
+
+    for (text, compare) in operators
+    {
+        if let Some(rest) = value.strip_prefix(text) {return (compare, rest);}
+    }
+    return (Compare::Equal, value);
+}
+
+pub fn compare<T: PartialOrd>(compare: Compare, a: T, b: T) -> bool
+{
+    match compare
+    {
+        Compare::Less => a < b,
+        Compare::LessEq => a <= b,
+        Compare::Equal => a == b,
+        Compare::GreaterEq => a >= b,
+        Compare::Greater => a > b,
+    }
+}
+
+// "2mb" -> bytes, sizes are in steps of 1024
+pub fn parse_filesize(value: &str) -> Option<u64>
+{
+    let units = [("gb", 1 << 30), ("mb", 1 << 20), ("kb", 1 << 10), ("b", 1)];
+    for (unit, factor) in units
+    {
+        if let Some(number) = value.strip_suffix(unit)
+        {
+            return number.trim().parse::<f64>().ok().map(|x| (x * factor as f64) as u64);
+        }
+    }
+    return value.parse::<u64>().ok();
+}
+
+pub fn parse_bool(value: &str) -> Option<bool>
+{
+    match value
+    {
+        "yes" | "true" | "1" => Some(true),
+        "no" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn is_glob(pattern: &str) -> bool
+{
+    return pattern.contains('*') || pattern.contains('?');
+}
+
+// '*' matches any run of characters, '?' matches exactly one.
+pub fn glob_match(pattern: &str, text: &str) -> bool
+{
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len()
+    {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t])
+        {
+            p += 1;
+            t += 1;
+        }
+        else if p < pattern.len() && pattern[p] == '*'
+        {
+            backtrack = Some((p, t));
+            p += 1;
+        }
+        else if let Some((star_p, star_t)) = backtrack
+        {
+            // let the last star swallow one more character and try again
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        }
+        else
+        {
+            return false;
+        }
+    }
+
+    while p < pattern.len() && pattern[p] == '*' {p += 1;}
+    return p == pattern.len();
+}
+
+pub fn parse(input: &String) -> Result<Query, String>
+{
+    let tokens = tokenize(input)?;
+    if tokens.len() == 0 {return Ok(Query::All);}
+
+    let mut parser = Parser{tokens: tokens, pos: 0, len: input.chars().count()};
+    let query = parser.parse_expr()?;
+
+    if parser.peek().is_some()
+    {
+        return Err(format!("unexpected ')' at {}", parser.position()));
+    }
+
+    return Ok(query);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn any(text: &str) -> Query
+    {
+        return Query::Term(Field::Any, text.to_string());
+    }
+
+    fn parse_str(input: &str) -> Result<Query, String>
+    {
+        return parse(&input.to_string());
+    }
+
+    fn error(input: &str) -> String
+    {
+        return parse_str(input).err().unwrap_or_default();
+    }
+
+    #[test]
+    fn precedence()
+    {
+        assert!(parse_str("") == Ok(Query::All));
+        assert!(parse_str("a b | c") == Ok(Query::Or(vec![Query::And(vec![any("a"), any("b")]), any("c")])));
+        assert!(parse_str("a (b | c)") == Ok(Query::And(vec![any("a"), Query::Or(vec![any("b"), any("c")])])));
+        assert!(parse_str("-a b") == Ok(Query::And(vec![Query::Not(Box::new(any("a"))), any("b")])));
+        assert!(parse_str("-(a | b)") == Ok(Query::Not(Box::new(Query::Or(vec![any("a"), any("b")])))));
+        assert!(parse_str("a-b") == Ok(any("a-b")));
+    }
+
+    #[test]
+    fn fields_and_quotes()
+    {
+        assert!(parse_str("tag:Hand") == Ok(Query::Term(Field::Tag, "hand".to_string())));
+        assert!(parse_str("artist:\"some name\"") == Ok(Query::Term(Field::Artist, "some name".to_string())));
+        assert!(parse_str("\"two words\"") == Ok(any("two words")));
+        assert!(parse_str("pose:standing") == Ok(any("pose:standing")));
+        assert!(parse_str("width:>=2000") == Ok(Query::Term(Field::Width, ">=2000".to_string())));
+    }
+
+    #[test]
+    fn errors()
+    {
+        assert!(error("a |").contains("nothing on its right side"));
+        assert!(error("| a").contains("nothing on its left side"));
+        assert!(error("(a").contains("missing ')' for the '(' at 1"));
+        assert!(error("a)").contains("unexpected ')' at 2"));
+        assert!(error("()").contains("empty group at 1"));
+        assert!(error("a -").contains("'-' at 3 is not followed by a term"));
+        assert!(error("\"a").contains("missing closing quote"));
+        assert!(error("tag:").contains("needs a value"));
+        assert!(error("width:big").contains("needs a number"));
+        assert!(error("orientation:round").contains("landscape, portrait or square"));
+    }
+
+    #[test]
+    fn quoting_terms()
+    {
+        assert!(quote_term("hand") == "hand");
+        assert!(quote_term("artist_(style)") == "\"artist_(style)\"");
+        assert!(quote_term("-dash") == "\"-dash\"");
+        assert!(quote_term("two words") == "\"two words\"");
+        assert!(parse_str(&quote_term("artist_(style)")) == Ok(any("artist_(style)")));
+        assert!(parse_str(&format!("-artist:{}", quote_term("a (b)"))) ==
+                Ok(Query::Not(Box::new(Query::Term(Field::Artist, "a (b)".to_string())))));
+    }
+
+    #[test]
+    fn splitting_terms()
+    {
+        assert!(split_terms("a  -b (c | d) artist:\"e f\"") == vec!["a", "-b", "(c | d)", "artist:\"e f\""]);
+        assert!(split_terms("  ").len() == 0);
+    }
+
+    #[test]
+    fn globs()
+    {
+        assert!(glob_match("hand*", "hands"));
+        assert!(glob_match("hand*", "hand"));
+        assert!(!glob_match("hand*", "han"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*x", "xxx"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("*ab*ab", "abxabab"));
+        assert!(glob_match("pose:*", "pose:standing"));
+        assert!(glob_match("h?ll*", "héllo"));
+    }
+}
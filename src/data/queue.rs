@@ -0,0 +1,84 @@
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+use crate::data::{Backend, Property};
+
+/////////////////////////
+
+type Job = Box<dyn FnOnce(&mut dyn Backend) + Send>;
+
+pub struct WriteResult
+{
+    pub file: String,
+    pub property: Property,
+    pub result: Result<String, String>,
+}
+
+// runs everything that touches the backend on a separate thread, in the order it was queued.
+// writes report back through poll(), reads block until the queue reaches them.
+pub struct WriteQueue
+{
+    job_tx: Option<mpsc::Sender<Job>>,
+    result_tx: mpsc::Sender<WriteResult>,
+    result_rx: mpsc::Receiver<WriteResult>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/////////////////////////
+
+impl Drop for WriteQueue
+{
+    fn drop(&mut self)
+    {
+        // closing the channel stops the thread once every queued write is done
+        self.job_tx.take();
+        if let Some(thread) = self.thread.take() {let _ = thread.join();}
+    }
+}
+
+impl WriteQueue
+{
+    pub fn new(mut backend: Box<dyn Backend>) -> WriteQueue
+    {
+        let (job_tx, job_rx) = mpsc::channel::<Job>();
+        let (result_tx, result_rx) = mpsc::channel();
+
+        let thread = thread::spawn(move ||
+        {
+            for job in job_rx {job(backend.as_mut());}
+        });
+
+        WriteQueue{
+            job_tx: Some(job_tx),
+            result_tx: result_tx,
+            result_rx: result_rx,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn read<T: Send + 'static>(&self, f: impl FnOnce(&mut dyn Backend) -> T + Send + 'static) -> Option<T>
+    {
+        let (tx, rx) = mpsc::channel();
+        let job: Job = Box::new(move |backend| {let _ = tx.send(f(backend));});
+
+        self.job_tx.as_ref()?.send(job).ok()?;
+        return rx.recv().ok();
+    }
+
+    pub fn write(&self, file: String, property: Property,
+                 f: impl FnOnce(&mut dyn Backend) -> Result<String, String> + Send + 'static) -> ()
+    {
+        let result_tx = self.result_tx.clone();
+        let job: Job = Box::new(move |backend| {
+            let result = f(backend);
+            let _ = result_tx.send(WriteResult{file: file, property: property, result: result});
+        });
+
+        if let Some(job_tx) = &self.job_tx {let _ = job_tx.send(job);}
+    }
+
+    pub fn poll(&self) -> Vec<WriteResult>
+    {
+        return self.result_rx.try_iter().collect();
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::PathBuf;
+use regex::Regex;
+use serde_json::{Value, json};
+
+use crate::data::config::Sidecar;
+
+/////////////////////////
+
+// metadata stored next to an image as <image>.json or <image>.xmp.
+// fields that are present replace the ones read from the image itself.
+pub struct SidecarData
+{
+    pub artists: Option<Vec<String>>,
+    pub links: Option<Vec<String>>,
+    pub tags: Option<Vec<String>>,
+    pub notes: Option<String>,
+}
+
+/////////////////////////
+
+pub fn sidecar_path(file: &String, kind: Sidecar) -> PathBuf
+{
+    match kind
+    {
+        Sidecar::Xmp => return PathBuf::from(format!("{}.xmp", file)),
+        _ => return PathBuf::from(format!("{}.json", file)),
+    }
+}
+
+fn xml_escape(text: &String) -> String
+{
+    return text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+}
+
+fn xml_unescape(text: &str) -> String
+{
+    return text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"")
+               .replace("&apos;", "'").replace("&amp;", "&");
+}
+
+fn json_list(json: &Value, key: &str) -> Option<Vec<String>>
+{
+    let list = json.get(key)?.as_array()?;
+    return Some(list.iter().filter_map(|x| x.as_str()).map(str::to_string).collect());
+}
+
+fn read_json(text: &String) -> Option<SidecarData>
+{
+    let json = serde_json::from_str::<Value>(text).ok()?;
+
+    Some(SidecarData{
+        artists: json_list(&json, "artists"),
+        links: json_list(&json, "links"),
+        tags: json_list(&json, "tags"),
+        notes: json.get("notes").and_then(|x| x.as_str()).map(str::to_string),
+    })
+}
+
+fn xmp_list(text: &String, element: &str) -> Option<Vec<String>>
+{
+    let re_element = Regex::new(&format!(r"(?s)<{0}>(.*?)</{0}>", element)).unwrap();
+    let re_item = Regex::new(r"(?s)<rdf:li[^>]*>(.*?)</rdf:li>").unwrap();
+
+    let inner = re_element.captures(text)?.get(1)?.as_str();
+    return Some(re_item.captures_iter(inner).map(|x| xml_unescape(&x[1])).collect());
+}
+
+fn read_xmp(text: &String) -> Option<SidecarData>
+{
+    if !text.contains("<x:xmpmeta") {return None;}
+
+    Some(SidecarData{
+        artists: xmp_list(text, "dc:creator"),
+        links: xmp_list(text, "refviewer:links"),
+        tags: xmp_list(text, "dc:subject"),
+        notes: xmp_list(text, "exif:UserComment").map(|x| x.join("\n")),
+    })
+}
+
+fn xmp_items(items: &Vec<String>, container: &str) -> String
+{
+    let mut result = format!("<rdf:{}>", container);
+    for item in items
+    {
+        result.push_str(&format!("\n     <rdf:li>{}</rdf:li>", xml_escape(item)));
+    }
+    result.push_str(&format!("\n    </rdf:{}>", container));
+    return result;
+}
+
+fn build_xmp(artists: &Vec<String>, links: &Vec<String>, tags: &Vec<String>, notes: &String) -> String
+{
+    let mut xmp = String::new();
+    xmp.push_str("<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n");
+    xmp.push_str("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n");
+    xmp.push_str(" <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n");
+    xmp.push_str("  <rdf:Description rdf:about=\"\"\n");
+    xmp.push_str("    xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n");
+    xmp.push_str("    xmlns:exif=\"http://ns.adobe.com/exif/1.0/\"\n");
+    xmp.push_str("    xmlns:refviewer=\"https://github.com/andr00d/ref_viewer/\">\n");
+    xmp.push_str(&format!("   <dc:creator>\n    {}\n   </dc:creator>\n", xmp_items(artists, "Seq")));
+    xmp.push_str(&format!("   <dc:subject>\n    {}\n   </dc:subject>\n", xmp_items(tags, "Bag")));
+    xmp.push_str(&format!("   <refviewer:links>\n    {}\n   </refviewer:links>\n", xmp_items(links, "Bag")));
+    xmp.push_str("   <exif:UserComment>\n    <rdf:Alt>\n");
+    xmp.push_str(&format!("     <rdf:li xml:lang=\"x-default\">{}</rdf:li>\n", xml_escape(notes)));
+    xmp.push_str("    </rdf:Alt>\n   </exif:UserComment>\n");
+    xmp.push_str("  </rdf:Description>\n </rdf:RDF>\n</x:xmpmeta>\n<?xpacket end=\"w\"?>\n");
+    return xmp;
+}
+
+/////////////////////////
+
+// when an image has both kinds of sidecar, the most recently written one is used
+pub fn read(file: &String) -> Option<SidecarData>
+{
+    let json = sidecar_path(file, Sidecar::Json);
+    let xmp = sidecar_path(file, Sidecar::Xmp);
+    let modified = |x: &PathBuf| std::fs::metadata(x).and_then(|x| x.modified()).ok();
+
+    let (json_time, xmp_time) = (modified(&json), modified(&xmp));
+    if json_time.is_none() && xmp_time.is_none() {return None;}
+
+    if xmp_time.is_none() || (json_time.is_some() && json_time >= xmp_time)
+    {
+        return read_json(&std::fs::read_to_string(json).ok()?);
+    }
+
+    return read_xmp(&std::fs::read_to_string(xmp).ok()?);
+}
+
+pub fn write(file: &String, kind: Sidecar, artists: &Vec<String>, links: &Vec<String>,
+             tags: &Vec<String>, notes: &String) -> Result<String, String>
+{
+    let text = match kind
+    {
+        Sidecar::Xmp => build_xmp(artists, links, tags, notes),
+        Sidecar::Json =>
+        {
+            let json = json!({"artists": artists, "links": links, "tags": tags, "notes": notes});
+            serde_json::to_string_pretty(&json).unwrap()
+        },
+        Sidecar::Off => return Err("sidecar files are turned off".to_string()),
+    };
+
+    let path = sidecar_path(file, kind);
+    match std::fs::write(&path, text)
+    {
+        Ok(_) => return Ok(format!("wrote {}", path.to_string_lossy())),
+        Err(x) => return Err(format!("could not write {}: {}", path.to_string_lossy(), x)),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn list(items: &[&str]) -> Vec<String>
+    {
+        return items.iter().map(|x| x.to_string()).collect();
+    }
+
+    #[test]
+    fn xmp_round_trip()
+    {
+        let artists = list(&["someone & co", "<anon>"]);
+        let links = list(&["https://example.com/a?b=1&c=\"2\""]);
+        let tags = list(&["hand", "pose:sitting", "a < b", "&lt; stays"]);
+        let notes = "first line with \"quotes\"\nsecond & <third>\n\n  indented".to_string();
+
+        let xmp = build_xmp(&artists, &links, &tags, &notes);
+        let data = read_xmp(&xmp).expect("xmp not read back");
+        assert_eq!(data.artists, Some(artists));
+        assert_eq!(data.links, Some(links));
+        assert_eq!(data.tags, Some(tags));
+        assert_eq!(data.notes, Some(notes));
+    }
+
+    #[test]
+    fn xmp_empty_lists()
+    {
+        let data = read_xmp(&build_xmp(&Vec::new(), &Vec::new(), &Vec::new(), &String::new())).unwrap();
+        assert_eq!(data.artists, Some(Vec::new()));
+        assert_eq!(data.tags, Some(Vec::new()));
+        assert_eq!(data.notes, Some(String::new()));
+    }
+}
@@ -0,0 +1,442 @@
+/////////////////////////
+
+// minimal reader/writer for the tiff structure exif data is stored in.
+// entries keep their raw bytes, so anything we don't understand is copied as-is.
+// offsets stored inside values (like some makernotes use) are not fixed up.
+
+pub const TAG_DESCRIPTION: u16 = 0x010E;
+pub const TAG_PAGE_NAME: u16 = 0x011D;
+pub const TAG_ARTIST: u16 = 0x013B;
+pub const TAG_USER_COMMENT: u16 = 0x9286;
+pub const TAG_ORIENTATION: u16 = 0x0112;
+
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_INTEROP_IFD: u16 = 0xA005;
+const TAG_THUMB_OFFSET: u16 = 0x0201;
+const TAG_THUMB_LENGTH: u16 = 0x0202;
+
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_UNDEFINED: u16 = 7;
+
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Clone)]
+pub struct Entry
+{
+    pub tag: u16,
+    pub kind: u16,
+    pub count: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone)]
+#[derive(Default)]
+pub struct Ifd
+{
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Clone)]
+pub struct Exif
+{
+    pub big_endian: bool,
+    pub ifd0: Ifd,
+    pub exif: Option<Ifd>,
+    pub gps: Option<Ifd>,
+    pub interop: Option<Ifd>,
+    pub ifd1: Option<Ifd>,
+    pub thumbnail: Option<Vec<u8>>,
+    // the ifd following ifd0, kept when rewriting tiff files in place
+    ifd0_next: u32,
+}
+
+/////////////////////////
+
+fn type_size(kind: u16) -> Option<usize>
+{
+    match kind
+    {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 | 13 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize, big_endian: bool) -> Option<u16>
+{
+    let bytes: [u8; 2] = data.get(pos..pos+2)?.try_into().ok()?;
+    if big_endian {return Some(u16::from_be_bytes(bytes));}
+    return Some(u16::from_le_bytes(bytes));
+}
+
+fn read_u32(data: &[u8], pos: usize, big_endian: bool) -> Option<u32>
+{
+    let bytes: [u8; 4] = data.get(pos..pos+4)?.try_into().ok()?;
+    if big_endian {return Some(u32::from_be_bytes(bytes));}
+    return Some(u32::from_le_bytes(bytes));
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16, big_endian: bool) -> ()
+{
+    if big_endian {out.extend_from_slice(&value.to_be_bytes());}
+    else {out.extend_from_slice(&value.to_le_bytes());}
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32, big_endian: bool) -> ()
+{
+    if big_endian {out.extend_from_slice(&value.to_be_bytes());}
+    else {out.extend_from_slice(&value.to_le_bytes());}
+}
+
+fn long_bytes(value: u32, big_endian: bool) -> Vec<u8>
+{
+    let mut out = Vec::new();
+    push_u32(&mut out, value, big_endian);
+    return out;
+}
+
+fn read_ifd(data: &[u8], offset: u32, big_endian: bool) -> Option<(Ifd, u32)>
+{
+    let offset = offset as usize;
+    let count = read_u16(data, offset, big_endian)? as usize;
+    if count > MAX_ENTRIES {return None;}
+
+    let mut ifd = Ifd::default();
+    for i in 0..count
+    {
+        let pos = offset + 2 + i * 12;
+        let tag = read_u16(data, pos, big_endian)?;
+        let kind = read_u16(data, pos + 2, big_endian)?;
+        let count = read_u32(data, pos + 4, big_endian)?;
+
+        let entry_data = match type_size(kind)
+        {
+            Some(size) =>
+            {
+                let len = size.checked_mul(count as usize)?;
+                if len <= 4 {data.get(pos+8..pos+8+len)?.to_vec()}
+                else
+                {
+                    let value_pos = read_u32(data, pos + 8, big_endian)? as usize;
+                    match data.get(value_pos..value_pos.checked_add(len)?)
+                    {
+                        Some(x) => x.to_vec(),
+                        None => continue, // broken entry, drop it
+                    }
+                }
+            },
+            // unknown type, keep the raw value field
+            None => data.get(pos+8..pos+12)?.to_vec(),
+        };
+
+        ifd.entries.push(Entry{tag: tag, kind: kind, count: count, data: entry_data});
+    }
+
+    let next = read_u32(data, offset + 2 + count * 12, big_endian).unwrap_or(0);
+    return Some((ifd, next));
+}
+
+// appends the ifd (and its out-of-line values) to the end of out, returns its offset.
+fn write_ifd(out: &mut Vec<u8>, ifd: &Ifd, next: u32, big_endian: bool) -> u32
+{
+    if out.len() % 2 == 1 {out.push(0);}
+
+    let mut entries = ifd.entries.clone();
+    entries.sort_by_key(|x| x.tag);
+
+    let start = out.len();
+    let mut value_pos = start + 2 + entries.len() * 12 + 4;
+    let mut values = Vec::<u8>::new();
+
+    push_u16(out, entries.len() as u16, big_endian);
+    for entry in &entries
+    {
+        push_u16(out, entry.tag, big_endian);
+        push_u16(out, entry.kind, big_endian);
+        push_u32(out, entry.count, big_endian);
+
+        if entry.data.len() <= 4
+        {
+            let mut inline = entry.data.clone();
+            inline.resize(4, 0);
+            out.extend_from_slice(&inline);
+        }
+        else
+        {
+            push_u32(out, value_pos as u32, big_endian);
+            values.extend_from_slice(&entry.data);
+            if entry.data.len() % 2 == 1 {values.push(0);}
+            value_pos = start + 2 + entries.len() * 12 + 4 + values.len();
+        }
+    }
+
+    push_u32(out, next, big_endian);
+    out.extend_from_slice(&values);
+    return start as u32;
+}
+
+/////////////////////////
+
+impl Ifd
+{
+    pub fn get(&self, tag: u16) -> Option<&Entry>
+    {
+        return self.entries.iter().find(|x| x.tag == tag);
+    }
+
+    pub fn set(&mut self, entry: Entry) -> ()
+    {
+        self.remove(entry.tag);
+        self.entries.push(entry);
+    }
+
+    pub fn remove(&mut self, tag: u16) -> ()
+    {
+        self.entries.retain(|x| x.tag != tag);
+    }
+
+    fn get_long(&self, tag: u16, big_endian: bool) -> Option<u32>
+    {
+        let entry = self.get(tag)?;
+        if entry.kind != TYPE_LONG && entry.kind != 13 {return None;}
+        return read_u32(&entry.data, 0, big_endian);
+    }
+}
+
+impl Exif
+{
+    pub fn empty(big_endian: bool) -> Exif
+    {
+        Exif{
+            big_endian: big_endian,
+            ifd0: Ifd::default(),
+            exif: None,
+            gps: None,
+            interop: None,
+            ifd1: None,
+            thumbnail: None,
+            ifd0_next: 0,
+        }
+    }
+
+    pub fn parse(data: &[u8]) -> Option<Exif>
+    {
+        let big_endian = match data.get(0..4)?
+        {
+            [b'I', b'I', 42, 0] => false,
+            [b'M', b'M', 0, 42] => true,
+            _ => return None,
+        };
+
+        let ifd0_offset = read_u32(data, 4, big_endian)?;
+        let (ifd0, next) = read_ifd(data, ifd0_offset, big_endian)?;
+        let mut exif = Exif::empty(big_endian);
+        exif.ifd0_next = next;
+
+        if let Some(offset) = ifd0.get_long(TAG_EXIF_IFD, big_endian)
+        {
+            exif.exif = read_ifd(data, offset, big_endian).map(|x| x.0);
+        }
+        if let Some(offset) = ifd0.get_long(TAG_GPS_IFD, big_endian)
+        {
+            exif.gps = read_ifd(data, offset, big_endian).map(|x| x.0);
+        }
+        if let Some(offset) = exif.exif.as_ref().and_then(|x| x.get_long(TAG_INTEROP_IFD, big_endian))
+        {
+            exif.interop = read_ifd(data, offset, big_endian).map(|x| x.0);
+        }
+
+        if next != 0 && next != ifd0_offset
+        {
+            if let Some((ifd1, _)) = read_ifd(data, next, big_endian)
+            {
+                let offset = ifd1.get_long(TAG_THUMB_OFFSET, big_endian);
+                let length = ifd1.get_long(TAG_THUMB_LENGTH, big_endian);
+                if let (Some(offset), Some(length)) = (offset, length)
+                {
+                    let (offset, length) = (offset as usize, length as usize);
+                    exif.thumbnail = data.get(offset..offset+length).map(|x| x.to_vec());
+                }
+                exif.ifd1 = Some(ifd1);
+            }
+        }
+
+        exif.ifd0 = ifd0;
+        return Some(exif);
+    }
+
+    ///////////////////
+    // field access  //
+    ///////////////////
+
+    pub fn get_string(&self, tag: u16) -> Option<String>
+    {
+        let entry = match tag
+        {
+            TAG_USER_COMMENT => return self.get_comment(),
+            _ => self.ifd0.get(tag)?,
+        };
+
+        let end = entry.data.iter().position(|x| *x == 0).unwrap_or(entry.data.len());
+        return Some(String::from_utf8_lossy(&entry.data[..end]).into_owned());
+    }
+
+    pub fn get_short(&self, tag: u16) -> Option<u16>
+    {
+        let entry = self.ifd0.get(tag)?;
+        if entry.kind != TYPE_SHORT {return None;}
+        return read_u16(&entry.data, 0, self.big_endian);
+    }
+
+    pub fn set_string(&mut self, tag: u16, value: &String) -> ()
+    {
+        if tag == TAG_USER_COMMENT {return self.set_comment(value);}
+
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+        self.ifd0.set(Entry{tag: tag, kind: TYPE_ASCII, count: data.len() as u32, data: data});
+    }
+
+    fn get_comment(&self) -> Option<String>
+    {
+        let entry = self.exif.as_ref()?.get(TAG_USER_COMMENT)?;
+        if entry.data.len() < 8 {return None;}
+        let (charset, text) = entry.data.split_at(8);
+
+        let value = match charset
+        {
+            b"UNICODE\0" =>
+            {
+                let units: Vec<u16> = text.chunks_exact(2)
+                    .map(|x| read_u16(x, 0, self.big_endian).unwrap())
+                    .collect();
+                String::from_utf16_lossy(&units)
+            },
+            _ => String::from_utf8_lossy(text).into_owned(),
+        };
+
+        return Some(value.trim_end_matches(['\0', ' ']).to_string());
+    }
+
+    fn set_comment(&mut self, value: &String) -> ()
+    {
+        let mut data = Vec::new();
+        if value.is_ascii()
+        {
+            data.extend_from_slice(b"ASCII\0\0\0");
+            data.extend_from_slice(value.as_bytes());
+        }
+        else
+        {
+            data.extend_from_slice(b"UNICODE\0");
+            for unit in value.encode_utf16() {push_u16(&mut data, unit, self.big_endian);}
+        }
+
+        let entry = Entry{tag: TAG_USER_COMMENT, kind: TYPE_UNDEFINED, count: data.len() as u32, data: data};
+        self.exif.get_or_insert_with(Ifd::default).set(entry);
+    }
+
+    ///////////////////
+    // serializing   //
+    ///////////////////
+
+    // writes the sub ifds and returns the pointer entries ifd0 should get for them
+    fn write_sub_ifds(&self, out: &mut Vec<u8>) -> Vec<Entry>
+    {
+        let be = self.big_endian;
+        let mut pointers = Vec::new();
+
+        if let Some(exif) = &self.exif
+        {
+            let mut exif = exif.clone();
+            exif.remove(TAG_INTEROP_IFD);
+            if let Some(interop) = &self.interop
+            {
+                let offset = write_ifd(out, interop, 0, be);
+                exif.set(Entry{tag: TAG_INTEROP_IFD, kind: TYPE_LONG, count: 1, data: long_bytes(offset, be)});
+            }
+
+            let offset = write_ifd(out, &exif, 0, be);
+            pointers.push(Entry{tag: TAG_EXIF_IFD, kind: TYPE_LONG, count: 1, data: long_bytes(offset, be)});
+        }
+
+        if let Some(gps) = &self.gps
+        {
+            let offset = write_ifd(out, gps, 0, be);
+            pointers.push(Entry{tag: TAG_GPS_IFD, kind: TYPE_LONG, count: 1, data: long_bytes(offset, be)});
+        }
+
+        return pointers;
+    }
+
+    // a standalone tiff block, as embedded in jpeg, png and webp files
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let be = self.big_endian;
+        let mut out = Vec::new();
+        if be {out.extend_from_slice(&[b'M', b'M', 0, 42]);}
+        else {out.extend_from_slice(&[b'I', b'I', 42, 0]);}
+        push_u32(&mut out, 0, be);
+
+        let pointers = self.write_sub_ifds(&mut out);
+
+        let mut next = 0;
+        if let Some(ifd1) = &self.ifd1
+        {
+            let mut ifd1 = ifd1.clone();
+            ifd1.remove(TAG_THUMB_OFFSET);
+            ifd1.remove(TAG_THUMB_LENGTH);
+
+            if let Some(thumbnail) = &self.thumbnail
+            {
+                if out.len() % 2 == 1 {out.push(0);}
+                let offset = out.len() as u32;
+                out.extend_from_slice(thumbnail);
+                ifd1.set(Entry{tag: TAG_THUMB_OFFSET, kind: TYPE_LONG, count: 1, data: long_bytes(offset, be)});
+                ifd1.set(Entry{tag: TAG_THUMB_LENGTH, kind: TYPE_LONG, count: 1, data: long_bytes(thumbnail.len() as u32, be)});
+            }
+            next = write_ifd(&mut out, &ifd1, 0, be);
+        }
+
+        let mut ifd0 = self.ifd0.clone();
+        ifd0.remove(TAG_EXIF_IFD);
+        ifd0.remove(TAG_GPS_IFD);
+        for pointer in pointers {ifd0.set(pointer);}
+        let offset = write_ifd(&mut out, &ifd0, next, be);
+
+        let header = long_bytes(offset, be);
+        out[4..8].copy_from_slice(&header);
+        return out;
+    }
+
+    // for tiff files the image data is referenced by offset, so instead of moving
+    // anything the changed ifds are appended and the header is pointed at them.
+    pub fn append_to(&self, file: &mut Vec<u8>, original: &Exif) -> ()
+    {
+        let be = self.big_endian;
+        let mut ifd0 = self.ifd0.clone();
+
+        let exif_changed = match (&self.exif, &original.exif)
+        {
+            (Some(a), Some(b)) => a.get(TAG_USER_COMMENT).map(|x| &x.data) != b.get(TAG_USER_COMMENT).map(|x| &x.data),
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+        if exif_changed
+        {
+            // only the exif ifd itself moves, the interop ifd it points to stays where it is
+            let offset = write_ifd(file, self.exif.as_ref().unwrap(), 0, be);
+            ifd0.set(Entry{tag: TAG_EXIF_IFD, kind: TYPE_LONG, count: 1, data: long_bytes(offset, be)});
+        }
+
+        let offset = write_ifd(file, &ifd0, original.ifd0_next, be);
+        let header = long_bytes(offset, be);
+        file[4..8].copy_from_slice(&header);
+    }
+}
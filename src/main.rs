@@ -26,6 +26,8 @@ fn eframe_options(img: RgbaImage, w: u32, h: u32) -> eframe::NativeOptions
         viewport: egui::ViewportBuilder::default()
                 .with_inner_size([1000.0, 600.0])
                 .with_min_inner_size([300.0, 150.0])
+                // the pop out window can only be see-through if the main one asks for it
+                .with_transparent(true)
                 .with_icon(egui::IconData 
                     { 
                         rgba: img.into_raw(), 
@@ -2,7 +2,8 @@ pub mod shared;
 
 use std::time::Instant;
 
-use crate::data::image::Index;
+use crate::data::image::{Index, Tone};
+use crate::data::query::Query;
 
 #[derive(PartialEq)]
 #[derive(Clone)]
@@ -15,6 +16,40 @@ pub enum Textbox
     Notes,
 }
 
+// suggestion list of the focused textbox
+#[derive(Default)]
+pub struct Completion
+{
+    pub open: bool,
+    pub index: Option<usize>,
+    pub last: String,
+    pub dismissed: String,
+}
+
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Copy)]
+pub enum Sort
+{
+    Name,
+    Modified,
+    FileSize,
+    Pixels,
+    Aspect,
+    TagCount,
+    Shuffle,
+}
+
+// non-destructive changes to how the main image is shown
+#[derive(Default)]
+#[derive(Clone)]
+pub struct View
+{
+    pub flip: bool,
+    pub rotation: u8,
+    pub tone: Tone,
+}
+
 #[derive(PartialEq)]
 pub enum Gallery
 {
@@ -26,14 +61,33 @@ pub struct Shared
 {
     pub main_img: Index,
     pub active_input: Option<Textbox>,
+    pub completion: Completion,
     pub snap_to_index: bool,
     pub gallery_type: Gallery,
     pub last_update: Instant,
     pub frame_index: usize,
+    pub view: View,
+    pub fullscreen: bool,
     pub search: String,
+    pub search_error: Option<String>,
+    pub search_name: String,
+    pub history_pos: Option<usize>,
+    pub history_draft: String,
     pub key_event: Option<egui::Key>,
+    pub sort: Sort,
+    pub sort_descending: bool,
+    pub shuffle_seed: u64,
+    pub shuffle_play: bool,
+    shuffle_bag: Vec<Index>,
+    shuffle_pos: usize,
+    rng: u64,
     pub show_popup_about: bool,
     pub show_popup_help: bool,
+    pub show_popup_tags: bool,
+    pub show_tag_browser: bool,
+    last_query: Query,
+    searched: String,
+    search_timer: Option<Instant>,
     selected: Vec<Index>,
     selected_tags: [Vec<(String, usize)>; 3],
     results: Vec<Vec<Index>>,
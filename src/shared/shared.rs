@@ -1,10 +1,53 @@
 use std::collections::HashMap;
-use std::time::Instant;
-use regex::Regex;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::collections::hash_map::DefaultHasher;
+use std::time::{Duration, Instant};
 
-use crate::shared::{Shared, Gallery};
-use crate::data::image::Index;
+use crate::shared::{Shared, Gallery, Completion, Sort, View};
+use crate::data::image::{Index, Tone};
 use crate::data::Data;
+use crate::data::query::{self, Query, Field};
+
+// a search term without its sign and quotes, so foo, "foo" and -foo count as the same term
+fn term_key(term: &str) -> (bool, String)
+{
+    let negated = term.starts_with('-');
+    return (negated, term.trim_start_matches('-').replace('"', "").to_lowercase());
+}
+
+// compares numbers in names by value, so "img2" comes before "img10"
+fn natural_cmp(a: &str, b: &str) -> Ordering
+{
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop
+    {
+        match (a.peek().copied(), b.peek().copied())
+        {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() =>
+            {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut number = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {number.push(c);}
+                    number.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                let order = x.len().cmp(&y.len()).then(x.cmp(&y));
+                if order != Ordering::Equal {return order;}
+            },
+            (Some(x), Some(y)) =>
+            {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                if order != Ordering::Equal {return order;}
+                a.next();
+                b.next();
+            },
+        }
+    }
+}
 
 impl Shared 
 {
@@ -21,14 +64,34 @@ impl Shared
 
         Shared{main_img: index,
             active_input: None,
+            completion: Completion::default(),
             gallery_type: Gallery::LeftBar,
             snap_to_index: true,
             last_update: Instant::now(),
             frame_index: 0,
+            view: View::default(),
+            fullscreen: false,
             key_event: None,
             search: "".to_string(),
+            search_error: None,
+            search_name: "".to_string(),
+            history_pos: None,
+            history_draft: "".to_string(),
+            last_query: Query::All,
+            searched: "".to_string(),
+            search_timer: None,
+            sort: Sort::Name,
+            sort_descending: false,
+            shuffle_seed: 0,
+            shuffle_play: false,
+            shuffle_bag: Vec::new(),
+            shuffle_pos: 0,
+            rng: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                 .map(|x| x.as_nanos() as u64).unwrap_or(0) | 1,
             show_popup_about: false,
             show_popup_help: false,
+            show_popup_tags: false,
+            show_tag_browser: false,
             selected: selected,
             selected_tags: tags_array,
             results: imagelist,
@@ -78,6 +141,60 @@ impl Shared
         return Some(self.results[f][pos.unwrap()-1].clone());
     }
 
+    // xorshift, good enough for picking images
+    fn random(&mut self) -> u64
+    {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        return self.rng;
+    }
+
+    // every result once in a random order, then a new order. the current image goes last so it doesn't come up right away
+    fn fill_shuffle_bag(&mut self) -> ()
+    {
+        let mut bag: Vec<Index> = self.results.iter().flatten().filter(|x| **x != self.main_img).cloned().collect();
+        for i in (1..bag.len()).rev()
+        {
+            let j = (self.random() % (i as u64 + 1)) as usize;
+            bag.swap(i, j);
+        }
+        if self.results.iter().flatten().any(|x| *x == self.main_img) {bag.push(self.main_img.clone());}
+
+        self.shuffle_bag = bag;
+        self.shuffle_pos = 0;
+    }
+
+    pub fn random_result(&mut self) -> Option<Index>
+    {
+        if self.shuffle_pos >= self.shuffle_bag.len() {self.fill_shuffle_bag();}
+
+        let index = self.shuffle_bag.get(self.shuffle_pos)?.clone();
+        self.shuffle_pos += 1;
+        return Some(index);
+    }
+
+    // goes back through the images the shuffle already showed
+    fn prev_random(&mut self) -> Option<Index>
+    {
+        if self.shuffle_pos < 2 {return None;}
+
+        self.shuffle_pos -= 1;
+        return Some(self.shuffle_bag[self.shuffle_pos - 1].clone());
+    }
+
+    pub fn next_image(&mut self) -> Option<Index>
+    {
+        if self.shuffle_play {return self.random_result();}
+        return self.next_result(&self.main_img);
+    }
+
+    pub fn prev_image(&mut self) -> Option<Index>
+    {
+        if self.shuffle_play {return self.prev_random();}
+        return self.prev_result(&self.main_img);
+    }
+
     //////////////////////////
 
     pub fn update_tags(&mut self, img_data: &Data)
@@ -121,8 +238,18 @@ impl Shared
         let b_pos = self.results[b.folder].iter().position(|n| n == b);
         if a_pos.is_none() && b_pos.is_none() {return;}
 
+        // only one end is in the results, select just that one
+        if a_pos.is_none() || b_pos.is_none()
+        {
+            let index = if a_pos.is_some() {a.clone()} else {b.clone()};
+            self.selected = vec![index];
+            self.update_tags(img_data);
+            return;
+        }
+
+        // the range follows the order the results are shown in
         let (start, end); 
-        if a.folder < b.folder || (a.folder == b.folder && a.image < b.image)
+        if (a.folder, a_pos) < (b.folder, b_pos)
         {
             start = a.clone();
             end = b.clone();
@@ -157,24 +284,126 @@ impl Shared
 
     //////////////////////////
 
-    pub fn update_search(&mut self, img_data: &Data) -> ()
+    // quarter turns clockwise, the image is fitted to the window again
+    pub fn rotate_view(&mut self, img_data: &mut Data, turns: u8) -> ()
+    {
+        self.view.rotation = (self.view.rotation + turns) % 4;
+        if self.get_result_size() == 0 {return;}
+        img_data.folders[self.main_img.folder].images[self.main_img.image].transform = None;
+    }
+
+    pub fn toggle_grayscale(&mut self) -> ()
     {
-        let mut tags: Vec<String> = self.search.split_whitespace().map(str::to_string).collect();
-        let mut itags = tags.clone();
+        self.view.tone = if self.view.tone == Tone::Color {Tone::Grayscale} else {Tone::Color};
+    }
+
+    // goes through 2 to 5 values, then back to color
+    pub fn cycle_posterize(&mut self) -> ()
+    {
+        self.view.tone = match self.view.tone
+        {
+            Tone::Values(x) if x < 5 => Tone::Values(x + 1),
+            Tone::Values(_) => Tone::Color,
+            _ => Tone::Values(2),
+        };
+    }
+
+    pub fn reset_view(&mut self, img_data: &mut Data) -> ()
+    {
+        if self.view.rotation != 0 {self.rotate_view(img_data, 4 - self.view.rotation);}
+        self.view = View::default();
+    }
+
+    //////////////////////////
+
+    pub fn set_sort(&mut self, img_data: &Data, sort: Sort, descending: bool) -> ()
+    {
+        // picking shuffle again gives a new order
+        if sort == Sort::Shuffle && (self.sort != Sort::Shuffle || self.sort_descending == descending)
+        {
+            let time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+            self.shuffle_seed = time.as_nanos() as u64;
+        }
+
+        self.sort = sort;
+        self.sort_descending = descending;
+        self.sort_results(img_data);
+        self.snap_to_index = true;
+    }
+
+    fn sort_results(&mut self, img_data: &Data) -> ()
+    {
+        let shuffle_key = |file: &String| {
+            let mut hasher = DefaultHasher::new();
+            (self.shuffle_seed, file).hash(&mut hasher);
+            hasher.finish()
+        };
+
+        for folder in &mut self.results
+        {
+            folder.sort_by(|a, b| {
+                let a = &img_data.folders[a.folder].images[a.image];
+                let b = &img_data.folders[b.folder].images[b.image];
+                let pixels = |x: &crate::data::image::Image| x.width as u64 * x.height as u64;
+                let aspect = |x: &crate::data::image::Image| x.width as f64 / x.height.max(1) as f64;
+
+                let order = match self.sort
+                {
+                    Sort::Name => Ordering::Equal,
+                    Sort::Modified => a.modified.cmp(&b.modified),
+                    Sort::FileSize => a.file_size.cmp(&b.file_size),
+                    Sort::Pixels => pixels(a).cmp(&pixels(b)),
+                    Sort::Aspect => aspect(a).total_cmp(&aspect(b)),
+                    Sort::TagCount => a.tags.len().cmp(&b.tags.len()),
+                    Sort::Shuffle => shuffle_key(&a.file).cmp(&shuffle_key(&b.file)),
+                };
+
+                let order = order.then_with(|| natural_cmp(&a.file, &b.file));
+                if self.sort_descending {order.reverse()} else {order}
+            });
+        }
+    }
+
+    // runs the search once typing pauses, returns how long to wait when it still has to run
+    pub fn poll_search(&mut self, img_data: &Data) -> Option<Duration>
+    {
+        const DELAY: Duration = Duration::from_millis(200);
 
-        tags.retain(|x| !x.starts_with("-"));
-        itags.retain(|x| x.starts_with("-"));
-        for part in &mut itags{part.remove(0);}
+        if self.search == self.searched {self.search_timer = None; return None;}
+        let timer = *self.search_timer.get_or_insert(Instant::now());
+
+        if timer.elapsed() < DELAY {return Some(DELAY - timer.elapsed());}
+        self.update_search(img_data);
+        return None;
+    }
+
+    pub fn update_search(&mut self, img_data: &Data) -> ()
+    {
+        self.searched = self.search.clone();
+        self.search_timer = None;
 
+        // on a parse error, keep filtering on the last valid query so results still match the folders
+        match query::parse(&self.search)
+        {
+            Ok(x) => 
+            {
+                self.search_error = None;
+                self.last_query = x;
+            },
+            Err(x) => self.search_error = Some(x),
+        };
 
-        self.results = img_data.build_vector(tags, itags);
+        self.results = img_data.build_vector(&self.last_query);
+        self.sort_results(img_data);
+        self.shuffle_bag.clear();
+        self.shuffle_pos = 0;
 
         let mut count = 0;
         for folder in &self.results {count += folder.len();}
         self.results_len = count;
         if self.results_len == 0 {return;}
 
-        if !self.results[self.main_img.folder].contains(&self.main_img) && self.results.len() > 0
+        if self.results.get(self.main_img.folder).is_none_or(|x| !x.contains(&self.main_img))
         {
             let mut index = Index{folder: 0, image: 0};
             for folder in &self.results 
@@ -188,25 +417,44 @@ impl Shared
 
     pub fn rem_from_search(&mut self, img_data: &Data, tag: &String)
     {
-        self.search = str::replace(&self.search, tag, "");
+        let key = term_key(tag).1;
+        let mut terms = Vec::<String>::new();
 
-        let re = Regex::new(r"\s\s+").unwrap();
-        self.search = re.replace_all(&self.search, " ").to_string();
+        // a '|' needs something on both sides, so drop the ones that lost a neighbour
+        for term in query::split_terms(&self.search)
+        {
+            if term_key(&term).1 == key {continue;}
+            if term == "|" && terms.last().map_or(true, |x| x == "|") {continue;}
+            terms.push(term);
+        }
+        if terms.last().is_some_and(|x| x == "|") {terms.pop();}
 
+        self.search = terms.join(" ");
         self.update_search(img_data);
     }
 
     pub fn add_to_search(&mut self, img_data: &Data, tag: &String)
     {
-        if self.search.contains(tag) {return;}
-        self.search += &(" ".to_owned() + tag);
+        let (negated, key) = term_key(tag);
+        let mut terms = query::split_terms(&self.search);
 
-        let re = Regex::new(r"\s\s+").unwrap();
-        self.search = re.replace_all(&self.search, " ").to_string();
+        match terms.iter().position(|x| term_key(x).1 == key)
+        {
+            Some(i) if term_key(&terms[i]).0 == negated => return,
+            // the same term with the other sign gets flipped instead of contradicting it
+            Some(i) => terms[i] = tag.clone(),
+            None => terms.push(tag.clone()),
+        }
 
+        self.search = terms.join(" ");
         self.update_search(img_data);
     }
 
+    pub fn notes_terms(&self) -> Vec<String>
+    {
+        return query::field_terms(&self.last_query, &Field::Notes);
+    }
+
     pub fn get_results(&self) -> &Vec<Vec<Index>>
     {
         return &self.results;
@@ -2,9 +2,20 @@ mod wndw_right;
 mod wndw_toolbar;
 mod wndw_main;
 mod wndw_gallery;
+mod wndw_tags;
+mod wndw_browser;
+mod wndw_slideshow;
+mod wndw_popout;
+mod history;
+mod autocomplete;
 pub mod window;
 
 use wndw_right::WndwRight;
+use history::History;
+use wndw_tags::WndwTags;
+use wndw_browser::WndwBrowser;
+use wndw_slideshow::Slideshow;
+use wndw_popout::Popout;
 
 use crate::data::Data;
 use crate::shared::Shared;
@@ -19,5 +30,10 @@ struct RefViewer
     img_data: Data,
     data_shared: Shared,
     data_right: WndwRight,
+    history: History,
+    data_tags: WndwTags,
+    data_browser: WndwBrowser,
+    slideshow: Slideshow,
+    popout: Popout,
 }
 
@@ -0,0 +1,159 @@
+use eframe::egui;
+use egui::Key;
+
+use crate::data::Data;
+use crate::data::query;
+use crate::shared::{Shared, Textbox};
+
+/////////////////////////
+
+const MAX_SUGGESTIONS: usize = 8;
+
+enum Source
+{
+    Tags,
+    Artists,
+    Both,
+}
+
+/////////////////////////
+
+// lower is better: 0 for a prefix, 1 for a substring, 2 when the letters appear in order
+fn match_rank(candidate: &str, input: &str) -> Option<usize>
+{
+    if candidate.starts_with(input) {return Some(0);}
+    if candidate.contains(input) {return Some(1);}
+
+    let mut chars = candidate.chars();
+    for c in input.chars()
+    {
+        if !chars.any(|x| x == c) {return None;}
+    }
+    return Some(2);
+}
+
+// splits the search into everything before the term being typed and the term itself
+fn split_search(search: &String) -> Option<(String, String, Source)>
+{
+    let start = search.rfind(|c: char| " ()|".contains(c)).map(|x| x + 1).unwrap_or(0);
+    let word = search[start..].trim_start_matches('-');
+    let mut head = search[..search.len() - word.len()].to_string();
+    if word.contains('"') {return None;}
+
+    match word.split_once(':')
+    {
+        Some(("artist", value)) =>
+        {
+            head.push_str("artist:");
+            return Some((head, value.to_string(), Source::Artists));
+        },
+        Some(("tag", value)) =>
+        {
+            head.push_str("tag:");
+            return Some((head, value.to_string(), Source::Tags));
+        },
+        Some((prefix, _)) if query::is_field(prefix) => return None,
+        _ => return Some((head, word.to_string(), Source::Both)),
+    }
+}
+
+fn suggestions(img_data: &Data, source: Source, input: &String) -> Vec<(String, usize)>
+{
+    let input = input.to_lowercase();
+    let candidates = match source
+    {
+        Source::Tags => img_data.tag_counts(),
+        Source::Artists => img_data.artist_counts(),
+        Source::Both => [img_data.tag_counts(), img_data.artist_counts()].concat(),
+    };
+
+    let mut ranked: Vec<(usize, String, usize)> = candidates.into_iter()
+        .filter(|(item, _)| item.to_lowercase() != input)
+        .filter_map(|(item, count)| Some((match_rank(&item.to_lowercase(), &input)?, item, count)))
+        .collect();
+
+    ranked.sort_by(|a, b| a.0.cmp(&b.0).then(b.2.cmp(&a.2)).then(a.1.cmp(&b.1)));
+    ranked.dedup_by(|a, b| a.1 == b.1);
+    return ranked.into_iter().take(MAX_SUGGESTIONS).map(|x| (x.1, x.2)).collect();
+}
+
+// only the search needs quotes, the tag and artist boxes take the name as it is
+fn complete(head: &String, item: &String, boxtype: &Textbox) -> String
+{
+    if *boxtype != Textbox::Search {return item.clone();}
+    return format!("{}{}", head, query::quote_term(item));
+}
+
+/////////////////////////
+
+// shows suggestions under a focused textbox. up and down pick one, enter or a click fills it in.
+// needs to run before the textbox handles enter, so the completed text is what gets used.
+pub fn autocomplete(ui: &mut egui::Ui, resp: &egui::Response, text: &mut String, boxtype: Textbox,
+                    img_data: &Data, data_shared: &mut Shared) -> ()
+{
+    if data_shared.active_input != Some(boxtype.clone()) {return;}
+
+    let state = &mut data_shared.completion;
+    if *text != state.last {state.index = None;}
+    state.last = text.clone();
+    if *text == state.dismissed {return;}
+
+    let split = match boxtype
+    {
+        Textbox::Search => split_search(text),
+        Textbox::Tag => Some(("".to_string(), text.clone(), Source::Tags)),
+        Textbox::Artist => Some(("".to_string(), text.clone(), Source::Artists)),
+        _ => None,
+    };
+
+    let Some((head, word, source)) = split else { return };
+    if word.len() == 0 {return;}
+
+    let items = suggestions(img_data, source, &word);
+    if items.len() == 0 {return;}
+    state.open = true;
+
+    if ui.input(|i| i.key_pressed(Key::ArrowDown))
+    {
+        state.index = Some(state.index.map(|x| x + 1).unwrap_or(0).min(items.len() - 1));
+    }
+    if ui.input(|i| i.key_pressed(Key::ArrowUp))
+    {
+        state.index = state.index.and_then(|x| x.checked_sub(1));
+    }
+    if state.index.is_some_and(|x| x >= items.len()) {state.index = Some(items.len() - 1);}
+
+    if let Some(index) = state.index
+    {
+        if ui.input(|i| i.key_pressed(Key::Enter))
+        {
+            *text = complete(&head, &items[index].0, &boxtype);
+            state.dismissed = text.clone();
+            state.index = None;
+            state.open = false;
+            return;
+        }
+    }
+
+    let mut chosen = None;
+    egui::Area::new(resp.id.with("autocomplete"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(resp.rect.left_bottom())
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_min_width(resp.rect.width());
+                for (i, (item, count)) in items.iter().enumerate()
+                {
+                    let label = ui.selectable_label(state.index == Some(i), format!("({count}): {item}"));
+                    if label.clicked() {chosen = Some(item.clone());}
+                }
+            });
+        });
+
+    if let Some(item) = chosen
+    {
+        *text = complete(&head, &item, &boxtype);
+        state.dismissed = text.clone();
+        state.index = None;
+    }
+}
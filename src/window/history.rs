@@ -0,0 +1,176 @@
+use crate::data::Data;
+use crate::data::image::Index;
+use crate::shared::Shared;
+use crate::window::wndw_right::Action;
+
+/////////////////////////
+
+const MAX_HISTORY: usize = 100;
+
+// images are stored by path, so the history survives opening and closing folders
+enum Edit
+{
+    Change(Action, Vec<String>),
+    Tags(Vec<(String, Vec<String>, Vec<String>)>), // file, tags removed, tags added including implied ones
+    Notes(String, String, String), // file, notes before, notes after
+}
+
+#[derive(Default)]
+pub struct History
+{
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+/////////////////////////
+
+fn inverse(action: &Action) -> Action
+{
+    match action
+    {
+        Action::ArtistAdd(x) => Action::ArtistDel(x.clone()),
+        Action::ArtistDel(x) => Action::ArtistAdd(x.clone()),
+        Action::LinkAdd(x) => Action::LinkDel(x.clone()),
+        Action::LinkDel(x) => Action::LinkAdd(x.clone()),
+        Action::TagAdd(x) => Action::TagDel(x.clone()),
+        Action::TagDel(x) => Action::TagAdd(x.clone()),
+        other => other.clone(),
+    }
+}
+
+// applies an add/remove action, returns if the image actually changed
+fn apply_action(img_data: &mut Data, index: &Index, action: &Action) -> bool
+{
+    match action
+    {
+        Action::ArtistAdd(x) => return img_data.add_artist(index, x),
+        Action::ArtistDel(x) => return img_data.del_artist(index, x),
+        Action::LinkAdd(x) => return img_data.add_link(index, x),
+        Action::LinkDel(x) => return img_data.del_link(index, x),
+        Action::TagAdd(x) => return img_data.add_tag(index, x, false).len() > 0,
+        Action::TagDel(x) => return img_data.del_tag(index, x),
+        _ => return false,
+    }
+}
+
+fn set_notes(img_data: &mut Data, file: &String, notes: &String) -> ()
+{
+    let Some(index) = img_data.get_path_index(file) else { return };
+    img_data.folders[index.folder].images[index.image].notes = notes.clone();
+    img_data.set_notes(&index);
+}
+
+impl History
+{
+    // runs an edit on the selected images and remembers what changed
+    pub fn apply(&mut self, img_data: &mut Data, data_shared: &Shared, action: Action) -> ()
+    {
+        let action = match action
+        {
+            Action::TagAdd(x) => Action::TagAdd(x.to_lowercase()),
+            other => other,
+        };
+
+        let edit = match action
+        {
+            Action::SetNotes =>
+            {
+                let index = &data_shared.main_img;
+                let previous = img_data.set_notes(index);
+                let img = &img_data.folders[index.folder].images[index.image];
+                if previous == img.notes {return;}
+                Edit::Notes(img.file.clone(), previous, img.notes.clone())
+            },
+            // adding a tag can add the tags it implies as well, those need to go again on undo
+            Action::TagAdd(ref tag) =>
+            {
+                let mut added = Vec::new();
+                for index in data_shared.get_selected()
+                {
+                    let tags = img_data.add_tag(index, tag, true);
+                    if tags.len() == 0 {continue;}
+                    added.push((img_data.folders[index.folder].images[index.image].file.clone(), Vec::new(), tags));
+                }
+                if added.len() == 0 {return;}
+                Edit::Tags(added)
+            },
+            _ =>
+            {
+                let mut files = Vec::new();
+                for index in data_shared.get_selected()
+                {
+                    if apply_action(img_data, index, &action)
+                    {
+                        files.push(img_data.folders[index.folder].images[index.image].file.clone());
+                    }
+                }
+                if files.len() == 0 {return;}
+                Edit::Change(action, files)
+            },
+        };
+
+        self.undo.push(edit);
+        if self.undo.len() > MAX_HISTORY {self.undo.remove(0);}
+        self.redo.clear();
+    }
+
+    // renames, merges or deletes tags on every image that has them, returns how many images changed
+    pub fn replace_tags(&mut self, img_data: &mut Data, tags: &Vec<String>, replacement: Option<&String>) -> usize
+    {
+        let changes: Vec<(String, Vec<String>, Vec<String>)> = img_data.replace_tags(tags, replacement).into_iter()
+            .map(|(index, removed, added)| (img_data.folders[index.folder].images[index.image].file.clone(), removed, added))
+            .collect();
+
+        let count = changes.len();
+        if count == 0 {return 0;}
+
+        self.undo.push(Edit::Tags(changes));
+        if self.undo.len() > MAX_HISTORY {self.undo.remove(0);}
+        self.redo.clear();
+        return count;
+    }
+
+    fn replay(img_data: &mut Data, edit: &Edit, reverse: bool) -> ()
+    {
+        match edit
+        {
+            Edit::Change(action, files) =>
+            {
+                let action = if reverse {inverse(action)} else {action.clone()};
+                for file in files
+                {
+                    let Some(index) = img_data.get_path_index(file) else { continue };
+                    apply_action(img_data, &index, &action);
+                }
+            },
+            Edit::Tags(changes) =>
+            {
+                for (file, removed, added) in changes
+                {
+                    let Some(index) = img_data.get_path_index(file) else { continue };
+                    if reverse {img_data.change_tags(&index, added, removed);}
+                    else {img_data.change_tags(&index, removed, added);}
+                }
+            },
+            Edit::Notes(file, before, after) => set_notes(img_data, file, if reverse {before} else {after}),
+        }
+    }
+
+    pub fn undo(&mut self, img_data: &mut Data, data_shared: &mut Shared) -> ()
+    {
+        let Some(edit) = self.undo.pop() else { return };
+        Self::replay(img_data, &edit, true);
+        if let Edit::Tags(_) = edit {data_shared.update_search(img_data);}
+        self.redo.push(edit);
+        data_shared.update_tags(img_data);
+    }
+
+    pub fn redo(&mut self, img_data: &mut Data, data_shared: &mut Shared) -> ()
+    {
+        let Some(edit) = self.redo.pop() else { return };
+        Self::replay(img_data, &edit, false);
+        if let Edit::Tags(_) = edit {data_shared.update_search(img_data);}
+        self.undo.push(edit);
+        data_shared.update_tags(img_data);
+    }
+}
@@ -1,21 +1,29 @@
-use egui::Key;
+use std::time::Duration;
+use egui::{Key, KeyboardShortcut, Modifiers};
 
 use crate::window::{RefViewer, ErrorWindow};
-use crate::shared::{Shared, Gallery};
+use crate::shared::{Shared, Gallery, Textbox};
 use crate::data::Data;
 use crate::data::image::Index;
+use crate::data::query::Query;
 use crate::window::{WndwRight, wndw_right};
 use crate::window::wndw_toolbar;
 use crate::window::wndw_main;
 use crate::window::wndw_gallery;
+use crate::window::history::History;
+use crate::window::wndw_tags::{self, WndwTags};
+use crate::window::wndw_browser::{self, WndwBrowser};
+use crate::window::wndw_slideshow::Slideshow;
+use crate::window::wndw_popout::Popout;
 
 
 impl RefViewer
 {
     fn new(mut img_data: Data, index: Index) -> Self 
     {
-        let imagelist = img_data.build_vector(Vec::new(), Vec::new());
+        let imagelist = img_data.build_vector(&Query::All);
         let mut data_shared = Shared::new(imagelist, index.clone());
+        data_shared.update_search(&img_data);
         
         if data_shared.get_result_size() > 0 
         {
@@ -28,6 +36,11 @@ impl RefViewer
             data_right: WndwRight{artist: "".to_string(), 
                                   link: "".to_string(), 
                                   tag: "".to_string()},
+            history: History::default(),
+            data_tags: WndwTags::default(),
+            data_browser: WndwBrowser::default(),
+            slideshow: Slideshow::new(),
+            popout: Popout::new(),
         }
     }
 }
@@ -36,35 +49,86 @@ impl eframe::App for RefViewer
 {
     fn update(&mut self, ui: &egui::Context, _frame: &mut eframe::Frame) 
     {
+        if self.img_data.poll_writes() {ui.request_repaint_after(Duration::from_millis(100));}
+
         get_inputs(ui, &mut self.data_shared);
         handle_inputs(ui, &mut self.img_data, &mut self.data_shared);
-        wndw_toolbar::wndw_toolbar(ui, &mut self.img_data, &mut self.data_shared);
-        
-        if self.data_shared.gallery_type == Gallery::Full
+        handle_history(ui, &mut self.img_data, &mut self.data_shared, &mut self.history);
+        // fullscreen only shows the image, the rest of the interface comes back when leaving it
+        if self.data_shared.fullscreen
         {
-            if self.data_shared.get_result_size() > 0
-            {
-                wndw_right::wndw_right(ui, &mut self.img_data, &mut self.data_shared, &mut self.data_right);
-            }
-            wndw_gallery::wndw_gallery(ui, &mut self.img_data, &mut self.data_shared);
+            if self.data_shared.get_result_size() == 0 {wndw_main::wndw_main_empty(ui);}
+            else {wndw_main::wndw_main(ui, &mut self.img_data, &mut self.data_shared);}
         }
         else
         {
-            wndw_gallery::wndw_left(ui, &mut self.img_data, &mut self.data_shared);
-    
-            if self.data_shared.get_result_size() == 0
+            wndw_toolbar::wndw_toolbar(ui, &mut self.img_data, &mut self.data_shared, &mut self.slideshow, &mut self.popout);
+            if self.data_shared.show_popup_tags {wndw_tags::wndw_tags(ui, &mut self.img_data, &mut self.data_shared, &mut self.data_tags, &mut self.history);}
+            if self.data_shared.show_tag_browser {wndw_browser::wndw_browser(ui, &self.img_data, &mut self.data_shared, &mut self.data_browser);}
+            
+            if self.data_shared.gallery_type == Gallery::Full
             {
-                wndw_main::wndw_main_empty(ui);
+                if self.data_shared.get_result_size() > 0
+                {
+                    wndw_right::wndw_right(ui, &mut self.img_data, &mut self.data_shared, &mut self.data_right, &mut self.history);
+                }
+                wndw_gallery::wndw_gallery(ui, &mut self.img_data, &mut self.data_shared);
             }
             else
             {
-                wndw_right::wndw_right(ui, &mut self.img_data, &mut self.data_shared, &mut self.data_right);
-                wndw_main::wndw_main(ui, &mut self.img_data, &mut self.data_shared);
+                wndw_gallery::wndw_left(ui, &mut self.img_data, &mut self.data_shared);
+        
+                if self.data_shared.get_result_size() == 0
+                {
+                    wndw_main::wndw_main_empty(ui);
+                }
+                else
+                {
+                    wndw_right::wndw_right(ui, &mut self.img_data, &mut self.data_shared, &mut self.data_right, &mut self.history);
+                    wndw_main::wndw_main(ui, &mut self.img_data, &mut self.data_shared);
+                }
             }
         }
+
+        self.slideshow.update(ui, &mut self.img_data, &mut self.data_shared);
+        self.popout.update(ui, &mut self.img_data);
+    }
+
+    // the panels cover the main window, this keeps the pop out window see-through
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4]
+    {
+        return [0.0, 0.0, 0.0, 0.0];
     }
 }
 
+pub fn show_image(img_data: &mut Data, data_shared: &mut Shared, x: Index)
+{
+    img_data.folders[x.folder].images[x.image].clear_full();
+    data_shared.main_img = x.clone();
+    data_shared.frame_index = 0;
+    data_shared.set_selected(img_data, &x, &x);
+    data_shared.snap_to_index = true;
+}
+
+pub fn toggle_fullscreen(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shared)
+{
+    data_shared.fullscreen = !data_shared.fullscreen;
+    data_shared.active_input = None;
+    if data_shared.fullscreen && data_shared.get_result_size() > 0
+    {
+        data_shared.gallery_type = Gallery::LeftBar;
+        let index = data_shared.main_img.clone();
+        data_shared.set_selected(img_data, &index, &index);
+    }
+
+    // the image gets fitted to the new window size
+    if data_shared.get_result_size() > 0
+    {
+        img_data.folders[data_shared.main_img.folder].images[data_shared.main_img.image].transform = None;
+    }
+    ui.send_viewport_cmd(egui::viewport::ViewportCommand::Fullscreen(data_shared.fullscreen));
+}
+
 fn handle_inputs(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shared)
 {
     if data_shared.key_event.is_none() {return;}
@@ -74,36 +138,26 @@ fn handle_inputs(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shar
     {
         Key::ArrowUp | Key::ArrowLeft =>
         {
-            match data_shared.prev_result(&data_shared.main_img)
-            {
-                Some(x) => 
-                {
-                    img_data.folders[x.folder].images[x.image].clear_full();
-                    data_shared.main_img = x.clone();
-                    data_shared.frame_index = 0;
-                    data_shared.set_selected(img_data, &x, &x);
-                    data_shared.snap_to_index = true;
-                },
-                None => (),
-            }
+            if let Some(x) = data_shared.prev_image() {show_image(img_data, data_shared, x);}
         },
 
         Key::ArrowDown | Key::ArrowRight =>
         {
-            match data_shared.next_result(&data_shared.main_img)
-            {
-                Some(x) => 
-                {
-                    img_data.folders[x.folder].images[x.image].clear_full();
-                    data_shared.main_img = x.clone();
-                    data_shared.frame_index = 0;
-                    data_shared.set_selected(img_data, &x, &x);
-                    data_shared.snap_to_index = true;
-                },
-                None => (),
-            }
+            if let Some(x) = data_shared.next_image() {show_image(img_data, data_shared, x);}
+        },
+
+        Key::R =>
+        {
+            if let Some(x) = data_shared.random_result() {show_image(img_data, data_shared, x);}
         },
 
+        Key::H => data_shared.view.flip = !data_shared.view.flip,
+        Key::OpenBracket => data_shared.rotate_view(img_data, 3),
+        Key::CloseBracket => data_shared.rotate_view(img_data, 1),
+        Key::G => data_shared.toggle_grayscale(),
+        Key::P => data_shared.cycle_posterize(),
+        Key::F11 => toggle_fullscreen(ui, img_data, data_shared),
+
         Key::Escape =>
         {
             if data_shared.active_input != None
@@ -111,6 +165,11 @@ fn handle_inputs(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shar
                 data_shared.active_input = None;
             }
 
+            else if data_shared.fullscreen
+            {
+                toggle_fullscreen(ui, img_data, data_shared);
+            }
+
             else if data_shared.gallery_type == Gallery::LeftBar
             {
                 data_shared.gallery_type = Gallery::Full;
@@ -141,13 +200,48 @@ fn handle_inputs(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shar
     data_shared.key_event=None;
 }
 
+fn handle_history(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shared, history: &mut History)
+{
+    // textboxes have their own undo while typing
+    if data_shared.active_input == Some(Textbox::Notes) || ui.wants_keyboard_input() {return;}
+
+    let undo = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+    let redo = KeyboardShortcut::new(Modifiers::COMMAND, Key::Y);
+    let redo_alt = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+
+    // check the longer shortcut first, consuming ctrl+z would also match ctrl+shift+z
+    if ui.input_mut(|i| i.consume_shortcut(&redo_alt) || i.consume_shortcut(&redo))
+    {
+        history.redo(img_data, data_shared);
+    }
+    else if ui.input_mut(|i| i.consume_shortcut(&undo))
+    {
+        history.undo(img_data, data_shared);
+    }
+}
+
 fn get_inputs(ui: &egui::Context, data_shared: &mut Shared)
 {
     let valid_keys = [Key::ArrowDown, Key::ArrowLeft, Key::ArrowRight, Key::ArrowUp,
-                      Key::Escape, Key::Enter];
+                      Key::Escape, Key::Enter, Key::R, Key::H, Key::OpenBracket, Key::CloseBracket,
+                      Key::G, Key::P, Key::F11];
+
+    // while typing, keys belong to the textbox, its suggestions or the search history.
+    // not every textbox sets active_input, so also ask egui whether one has focus.
+    let typing = data_shared.active_input.is_some() || ui.wants_keyboard_input();
+    data_shared.completion.open = false;
 
     for key in valid_keys
     {
+        if typing && ![Key::Escape, Key::F11].contains(&key) {continue;}
+
+        // escape only leaves the textbox, instead of also leaving the image view
+        if typing && key == Key::Escape && data_shared.active_input.is_none()
+        {
+            if ui.input(|i| i.key_pressed(key)) {ui.memory_mut(|m| m.stop_text_input());}
+            continue;
+        }
+
         if ui.input(|i| i.key_pressed(key)) 
         {
             data_shared.key_event = Some(key);
@@ -0,0 +1,70 @@
+use eframe::egui;
+use egui::widget_text::RichText;
+
+use crate::data::Data;
+use crate::data::query;
+use crate::shared::Shared;
+
+/////////////////////////
+
+#[derive(Default)]
+pub struct WndwBrowser
+{
+    pub filter: String,
+    pub by_count: bool,
+}
+
+/////////////////////////
+
+fn browse_list(ui: &mut egui::Ui, img_data: &Data, data_shared: &mut Shared, state: &WndwBrowser,
+               mut items: Vec<(String, usize)>, prefix: &str) -> ()
+{
+    let filter = state.filter.to_lowercase();
+    items.retain(|x| x.0.to_lowercase().contains(&filter));
+    if state.by_count {items.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));}
+
+    for (item, count) in items
+    {
+        let mut text = RichText::new(format!("({count}): {item}"));
+        if let Some((name, _)) = img_data.config.split_namespace(&item)
+        {
+            let [r, g, b] = img_data.config.namespaces[name];
+            text = text.color(egui::Color32::from_rgb(r, g, b));
+        }
+
+        let term = format!("{prefix}{}", query::quote_term(&item));
+        let resp = ui.add(egui::Label::new(text).sense(egui::Sense::click()))
+                     .on_hover_text("click to search, right-click to exclude");
+
+        if resp.clicked() {data_shared.add_to_search(img_data, &term);}
+        if resp.secondary_clicked() {data_shared.add_to_search(img_data, &format!("-{term}"));}
+    }
+}
+
+pub fn wndw_browser(ui: &egui::Context, img_data: &Data, data_shared: &mut Shared, state: &mut WndwBrowser) -> ()
+{
+    egui::SidePanel::left("tag_browser")
+    .default_width(150.0)
+    .resizable(true)
+    .show(ui, |ui| {
+        ui.add(egui::TextEdit::singleline(&mut state.filter).hint_text("filter"));
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut state.by_count, false, "name");
+            ui.radio_value(&mut state.by_count, true, "count");
+        });
+        ui.add(egui::Separator::default());
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.add(egui::Label::new(RichText::new("artists")
+                .background_color(egui::Color32::from_black_alpha(100))
+                .size(10.0)));
+            browse_list(ui, img_data, data_shared, state, img_data.artist_counts(), "artist:");
+
+            ui.add(egui::Separator::default());
+            ui.add(egui::Label::new(RichText::new("tags")
+                .background_color(egui::Color32::from_black_alpha(100))
+                .size(10.0)));
+            browse_list(ui, img_data, data_shared, state, img_data.tag_counts(), "tag:");
+        });
+    });
+}
@@ -2,28 +2,81 @@ use std::time::Instant;
 use std::vec::Vec;
 use regex::Regex;
 use egui::Color32;
+use egui::widget_text::RichText;
 use eframe::egui::{self, Button};
 use egui_extras::{TableBuilder, Column};
 
 use crate::data::image::{Status, Index};
 use crate::shared::{Shared, Gallery, Textbox};
 use crate::data::Data;
+use crate::data::config::Sidecar;
+use crate::window::autocomplete::autocomplete;
 
 const ICON_SIZE: f32 = 100.0;
 
 /////////////////////////
 
+// up goes back to older searches, down forward again and finally to what was being typed
+fn search_history(ui: &egui::Ui, img_data: &Data, data_shared: &mut Shared) -> ()
+{
+    let history = &img_data.config.search_history;
+    let pos = if ui.input(|i| i.key_pressed(egui::Key::ArrowUp))
+    {
+        if history.len() == 0 {return;}
+        if data_shared.history_pos.is_none() {data_shared.history_draft = data_shared.search.clone();}
+        Some(data_shared.history_pos.map(|x| x + 1).unwrap_or(0).min(history.len() - 1))
+    }
+    else if ui.input(|i| i.key_pressed(egui::Key::ArrowDown))
+    {
+        if data_shared.history_pos.is_none() {return;}
+        data_shared.history_pos.and_then(|x| x.checked_sub(1))
+    }
+    else {return;};
+
+    data_shared.history_pos = pos;
+    data_shared.search = match pos
+    {
+        Some(x) => history[x].clone(),
+        None => data_shared.history_draft.clone(),
+    };
+    data_shared.completion.dismissed = data_shared.search.clone();
+}
+
 fn search_bar(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared) -> ()
 {
     let resp_search = ui.add(egui::TextEdit::singleline(&mut data_shared.search).hint_text("search tags"));
+    let mut search = data_shared.search.clone();
+    autocomplete(ui, &resp_search, &mut search, Textbox::Search, img_data, data_shared);
+    data_shared.search = search;
+
+    if let Some(error) = &data_shared.search_error
+    {
+        ui.add(egui::Label::new(RichText::new(error).color(Color32::LIGHT_RED).size(10.0)));
+    }
+    else if data_shared.search.trim().len() > 0
+    {
+        let count = data_shared.get_result_size();
+        let text = if count == 1 {"1 image".to_string()} else {format!("{} images", count)};
+        ui.add(egui::Label::new(RichText::new(text).size(10.0)));
+    }
     ui.add(egui::Separator::default());
 
-    let re = Regex::new(r"[^a-zA-Z\d\s\-_*():]").unwrap();
+    let re = Regex::new(r#"[^a-zA-Z\d\s\-_*?():|".,'!/<>=]"#).unwrap();
     data_shared.search = re.replace_all(&data_shared.search, "").to_string();
 
+    if resp_search.changed() {data_shared.history_pos = None;}
+    if data_shared.active_input == Some(Textbox::Search) && !data_shared.completion.open
+    {
+        search_history(ui, img_data, data_shared);
+    }
+
+    if let Some(wait) = data_shared.poll_search(img_data) {ui.ctx().request_repaint_after(wait);}
+
     if resp_search.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) 
     {
         data_shared.update_search(img_data);
+        if data_shared.search_error.is_none() {img_data.config.add_search_history(&data_shared.search);}
+        data_shared.history_pos = None;
     }
 
     if resp_search.gained_focus(){data_shared.active_input = Some(Textbox::Search);}
@@ -65,7 +118,9 @@ fn get_snap_index (ui: &egui::Ui, img_data: &Data, data_shared: &mut Shared) ->
 
         if f == data_shared.main_img.folder 
         {
-            index += (data_shared.main_img.image + columns - 1) / columns;
+            let main_img = &data_shared.main_img;
+            let pos = data_shared.get_results()[f].iter().position(|x| x == main_img).unwrap_or(0);
+            index += pos / columns;
             return Some(index);
         }
         else 
@@ -136,13 +191,29 @@ fn show_image(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared,
     let is_selected = data_shared.get_selected().contains(index);
     let texture = image.thumb_texture.clone().unwrap();
 
-    let img_response = 
+    let mut img_response = 
     ui.add_sized([ICON_SIZE, ICON_SIZE],
         egui::Button::image(&texture)
         .fill(Color32::TRANSPARENT)
         .selected(is_selected)
     );
 
+    // mark images with unsaved or failed tag changes
+    let write_color = if image.write_error.is_some() {Some(Color32::RED)}
+                      else if image.pending_writes > 0 {Some(Color32::ORANGE)}
+                      else {None};
+
+    if let Some(color) = write_color
+    {
+        let pos = img_response.rect.right_top() + egui::vec2(-8.0, 8.0);
+        ui.painter().circle_filled(pos, 4.0, color);
+    }
+
+    if let Some(error) = &image.write_error
+    {
+        img_response = img_response.on_hover_text(format!("saving tags failed: {}", error));
+    }
+
     // ony allow multi selection in gallery mode
     if img_response.clicked() && ui.input(|i| i.modifiers.command_only()) &&
         data_shared.gallery_type == Gallery::Full
@@ -184,6 +255,19 @@ fn show_folder(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared,
     if resp.clicked() { folder.collapsed = !folder.collapsed; }
 
     resp.context_menu(|ui| {
+        ui.menu_button("store tags in", |ui| {
+            let mut choice = img_data.config.folder_sidecar(&path);
+            ui.radio_value(&mut choice, None, "default");
+            ui.radio_value(&mut choice, Some(Sidecar::Off), "image files");
+            ui.radio_value(&mut choice, Some(Sidecar::Json), "json sidecar files");
+            ui.radio_value(&mut choice, Some(Sidecar::Xmp), "xmp sidecar files");
+
+            if choice != img_data.config.folder_sidecar(&path)
+            {
+                img_data.config.set_folder_sidecar(&path, choice);
+            }
+        });
+
         if ui.button("close folder").clicked() 
         {
             img_data.close_folder(data_shared, path);
@@ -4,23 +4,23 @@ use eframe::egui::Vec2;
 use egui::TextureHandle;
 use egui::emath::TSTransform;
 
-use crate::data::image::{Image, Status};
-use crate::shared::Shared;
+use crate::data::image::{Status, TextureData};
+use crate::shared::{Shared, View};
 use crate::data::Data;
 
-fn get_frame(ui: &mut egui::Ui, img: &mut Image, data_shared: &mut Shared) -> TextureHandle
+pub fn get_frame(ui: &mut egui::Ui, frames: &Vec<TextureData>, frame_index: &mut usize, last_update: &mut Instant) -> TextureHandle
 {
-    let texture = match img.full_texture.len()
+    let texture = match frames.len()
     {
-        1 => img.full_texture[0].image.clone(),
+        1 => frames[0].image.clone(),
         _ => 
         {
-            let delay = img.full_texture[data_shared.frame_index].delay;
+            let delay = frames[*frame_index].delay;
             
-            if  Instant::now().duration_since(data_shared.last_update).as_millis() > delay.into()
+            if  Instant::now().duration_since(*last_update).as_millis() > delay.into()
             {
-                data_shared.frame_index = (data_shared.frame_index + 1) % img.full_texture.len();
-                data_shared.last_update = Instant::now();
+                *frame_index = (*frame_index + 1) % frames.len();
+                *last_update = Instant::now();
                 ui.ctx().request_repaint();
             }
             else
@@ -28,7 +28,7 @@ fn get_frame(ui: &mut egui::Ui, img: &mut Image, data_shared: &mut Shared) -> Te
                 ui.ctx().request_repaint_after(Duration::from_millis(delay.into()));
             }
 
-            img.full_texture[data_shared.frame_index].image.clone()
+            frames[*frame_index].image.clone()
         },
     };
 
@@ -65,14 +65,23 @@ fn bounds_check(mut ts: TSTransform, img_size: Vec2, ui_size: Vec2, offset: Vec2
     return ts;
 }
 
-fn calc_transform(ui: &mut egui::Ui, img: &mut Image) -> (TSTransform, bool)
+// size of the image on screen, before zooming
+fn view_size(texture: &TextureHandle, view: &View) -> Vec2
+{
+    let size = texture.size_vec2();
+    if view.rotation % 2 == 1 {return Vec2{x: size.y, y: size.x};}
+    return size;
+}
+
+// pan and zoom, starting from the image fitted to the available space when there is no transform yet
+pub fn calc_transform(ui: &mut egui::Ui, texture: &TextureHandle, transform: Option<TSTransform>, view: &View) -> (TSTransform, bool)
 {
     let mut interacted = false;
     let ui_size = ui.available_size();
-    let img_size = img.full_texture[0].image.size_vec2();
+    let img_size = view_size(texture, view);
     let offset = ui.next_widget_position().to_vec2();
 
-    let mut transform = if img.transform == None
+    let mut transform = if transform == None
     {
         let mut ts = TSTransform::default();
 
@@ -84,7 +93,7 @@ fn calc_transform(ui: &mut egui::Ui, img: &mut Image) -> (TSTransform, bool)
         ts.translation += offset;
         ts
     }
-    else {img.transform.unwrap()};
+    else {transform.unwrap()};
 
     let (tid, rect) = ui.allocate_space(ui.available_size());
     let response = ui.interact(rect, tid, egui::Sense::click_and_drag());
@@ -120,15 +129,27 @@ fn calc_transform(ui: &mut egui::Ui, img: &mut Image) -> (TSTransform, bool)
     return (transform, interacted);
 }
 
-fn show_img_area(ui: &mut egui::Ui, texture: TextureHandle, transform: TSTransform)
+pub fn show_img_area(ui: &mut egui::Ui, texture: TextureHandle, transform: TSTransform, view: &View, tint: egui::Color32)
 {
-    let size = texture.size_vec2().to_pos2() * transform.scaling;
+    let size = view_size(&texture, view) * transform.scaling;
     let min = transform.translation.to_pos2();
-    let max = min + size.to_vec2();
-    let rect = egui::Rect{min:min, max:max};
+    let rect = egui::Rect{min:min, max:min + size};
 
-    // image should not capture responses, so use paint_at
-    egui::Image::new(&texture).paint_at(ui, rect);
+    // corners go clockwise from the top left, rotating shifts which part of the texture lands where
+    let corners = [rect.left_top(), rect.right_top(), rect.right_bottom(), rect.left_bottom()];
+    let uvs = [egui::pos2(0.0, 0.0), egui::pos2(1.0, 0.0), egui::pos2(1.0, 1.0), egui::pos2(0.0, 1.0)];
+    let mirror = if view.flip {[1, 0, 3, 2]} else {[0, 1, 2, 3]};
+
+    // image should not capture responses, so paint the mesh directly
+    let mut mesh = egui::Mesh::with_texture(texture.id());
+    for i in 0..4
+    {
+        let uv = uvs[(mirror[i] + 4 - view.rotation as usize) % 4];
+        mesh.vertices.push(egui::epaint::Vertex{pos: corners[i], uv: uv, color: tint});
+    }
+    mesh.add_triangle(0, 1, 2);
+    mesh.add_triangle(0, 2, 3);
+    ui.painter().add(egui::Shape::mesh(mesh));
 }
 
 
@@ -157,7 +178,7 @@ pub fn wndw_main(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shar
         {
             Status::Unloaded => 
             {
-                img.load_full(); 
+                img.load_full(data_shared.view.tone); 
             }
 
             Status::Loading =>
@@ -166,11 +187,18 @@ pub fn wndw_main(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shar
                 ui.put(window_area, egui::widgets::Spinner::new());
             }
                 
+            // the tone is part of the texture, so the kept frames get uploaded again when it changes
+            Status::Loaded if img.full_tone != data_shared.view.tone =>
+            {
+                img.retone_full(data_shared.view.tone);
+                ui.put(window_area, egui::widgets::Spinner::new());
+            }
+
             Status::Loaded => 
             {
-                let texture = get_frame(ui, img, data_shared);
-                let (transform, interacted) = calc_transform(ui, img);
-                show_img_area(ui, texture, transform);
+                let texture = get_frame(ui, &img.full_texture, &mut data_shared.frame_index, &mut data_shared.last_update);
+                let (transform, interacted) = calc_transform(ui, &texture, img.transform, &data_shared.view);
+                show_img_area(ui, texture, transform, &data_shared.view, egui::Color32::WHITE);
                 if interacted {img.transform = Some(transform);}
             }
 
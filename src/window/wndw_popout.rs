@@ -0,0 +1,178 @@
+use std::time::Instant;
+use eframe::egui;
+use egui::emath::TSTransform;
+use egui::widget_text::RichText;
+
+use crate::data::Data;
+use crate::data::image::{Index, Status, TextureData};
+use crate::shared::{Shared, View};
+use crate::window::wndw_main;
+
+/////////////////////////
+
+// a small window that keeps one image on screen while the rest of the app is out of the way
+pub struct Popout
+{
+    pub opacity: f32,
+    pub on_top: bool,
+    pub click_through: bool,
+    file: Option<String>,
+    frames: Vec<TextureData>,
+    transform: Option<TSTransform>,
+    view: View,
+    frame_index: usize,
+    last_update: Instant,
+}
+
+/////////////////////////
+
+impl Popout
+{
+    pub fn new() -> Popout
+    {
+        Popout{
+            opacity: 1.0,
+            on_top: true,
+            click_through: false,
+            file: None,
+            frames: Vec::new(),
+            transform: None,
+            view: View::default(),
+            frame_index: 0,
+            last_update: Instant::now(),
+        }
+    }
+
+    // pins the image as it is shown right now, including the view transforms
+    pub fn open(&mut self, img_data: &Data, data_shared: &Shared, index: Index) -> ()
+    {
+        self.file = Some(img_data.folders[index.folder].images[index.image].file.clone());
+        self.frames = Vec::new();
+        self.transform = None;
+        self.view = data_shared.view.clone();
+        self.frame_index = 0;
+    }
+
+    pub fn close(&mut self) -> ()
+    {
+        self.file = None;
+        self.frames = Vec::new();
+        self.click_through = false;
+    }
+
+    // the textures are shared with the image, so they stay valid after the main view unloads it
+    fn load(&mut self, ui: &mut egui::Ui, img_data: &mut Data, index: &Index) -> bool
+    {
+        if self.frames.len() > 0 {return true;}
+        let img = &mut img_data.folders[index.folder].images[index.image];
+
+        match img.full_state()
+        {
+            Status::Unloaded => {img.load_full(self.view.tone);},
+            Status::Loading => img.poll_full(ui),
+            Status::Loaded =>
+            {
+                self.frames = img.full_texture.iter()
+                    .map(|x| TextureData{image: x.image.clone(), delay: x.delay})
+                    .collect();
+                return true;
+            },
+            Status::Error =>
+            {
+                ui.label(format!("error loading {}", img.file));
+                return false;
+            },
+        }
+
+        ui.centered_and_justified(|ui| {ui.spinner();});
+        ui.ctx().request_repaint();
+        return false;
+    }
+
+    fn controls(&mut self, ui: &mut egui::Ui) -> ()
+    {
+        ui.add(egui::Slider::new(&mut self.opacity, 0.1..=1.0).text("opacity"));
+        ui.checkbox(&mut self.on_top, "always on top");
+        ui.checkbox(&mut self.click_through, "click through")
+          .on_hover_text("clicks go to the window behind it, turn this off again from the view menu");
+    }
+
+    fn contents(&mut self, ui: &mut egui::Ui, img_data: &mut Data, index: &Index) -> ()
+    {
+        if !self.load(ui, img_data, index) {return;}
+
+        let texture = wndw_main::get_frame(ui, &self.frames, &mut self.frame_index, &mut self.last_update);
+        let (transform, interacted) = wndw_main::calc_transform(ui, &texture, self.transform, &self.view);
+        let tint = egui::Color32::WHITE.gamma_multiply(self.opacity);
+        wndw_main::show_img_area(ui, texture, transform, &self.view, tint);
+        if interacted {self.transform = Some(transform);}
+
+        // a double click fits the image to the window again
+        let rect = ui.min_rect();
+        let response = ui.interact(rect, ui.id().with("popout_menu"), egui::Sense::click());
+        if response.double_clicked() {self.transform = None;}
+        response.context_menu(|ui| {
+            self.controls(ui);
+            ui.separator();
+            if ui.button("close").clicked() {self.close(); ui.close_menu();}
+        });
+    }
+
+    /////////////////////////
+
+    pub fn menu(&mut self, ui: &mut egui::Ui, img_data: &Data, data_shared: &Shared) -> ()
+    {
+        let enabled = data_shared.get_result_size() > 0;
+        if ui.add_enabled(enabled, egui::Button::new("pop out current image")).clicked()
+        {
+            self.open(img_data, data_shared, data_shared.main_img.clone());
+            ui.close_menu();
+        }
+
+        let Some(file) = &self.file else { return };
+        ui.add(egui::Label::new(RichText::new(file).size(10.0)));
+        self.controls(ui);
+        if ui.button("close pop out").clicked() {self.close();}
+    }
+
+    pub fn update(&mut self, ui: &egui::Context, img_data: &mut Data) -> ()
+    {
+        let Some(file) = &self.file else { return };
+
+        // positions shift when folders are closed, so look the image up by its path every time
+        let Some(index) = img_data.get_path_index(file) else { self.close(); return };
+
+        let level = if self.on_top {egui::WindowLevel::AlwaysOnTop} else {egui::WindowLevel::Normal};
+        let builder = egui::ViewportBuilder::default()
+            .with_title("ref viewer - pop out")
+            .with_inner_size([300.0, 300.0])
+            .with_min_inner_size([100.0, 100.0])
+            .with_transparent(true)
+            .with_window_level(level)
+            .with_mouse_passthrough(self.click_through);
+
+        ui.show_viewport_immediate(egui::ViewportId::from_hash_of("popout"), builder, |ui, class| {
+            let background = egui::Color32::from_black_alpha((self.opacity * 255.0) as u8);
+
+            // without support for more windows it gets drawn as a window inside the app instead
+            if class == egui::ViewportClass::Embedded
+            {
+                let mut open = true;
+                egui::Window::new("pop out").open(&mut open).default_size([300.0, 300.0])
+                    .frame(egui::Frame::window(&ui.style()).fill(background))
+                    .show(ui, |ui| {
+                        ui.set_min_size(ui.available_size());
+                        self.contents(ui, img_data, &index);
+                    });
+                if !open {self.close();}
+                return;
+            }
+
+            egui::CentralPanel::default().frame(egui::Frame::NONE.fill(background)).show(ui, |ui| {
+                self.contents(ui, img_data, &index);
+            });
+
+            if ui.input(|i| i.viewport().close_requested()) {self.close();}
+        });
+    }
+}
@@ -4,6 +4,10 @@ use egui::widget_text::RichText;
 
 use crate::data::Data;
 use crate::shared::{Shared, Textbox};
+use crate::data::image::Index;
+use crate::data::query;
+use crate::window::history::History;
+use crate::window::autocomplete::autocomplete;
 
 /////////////////////////
 
@@ -14,6 +18,7 @@ pub struct WndwRight
     pub tag: String,
 }
 
+#[derive(Clone)]
 pub enum Action
 {
     ArtistAdd(String),
@@ -38,7 +43,39 @@ fn add_button(ui: &mut egui::Ui, text: &str) -> bool
     return ui.add(egui::Button::new(text).min_size(size).fill(color)).clicked() 
 }
 
-fn display_notes(ui: &mut egui::Ui, notes: &mut String, active: &mut Option<Textbox>) -> Option<Action>
+// byte ranges of the notes that match the notes: terms of the search
+fn note_matches(notes: &str, terms: &Vec<String>) -> Vec<(usize, usize)>
+{
+    let lower = notes.to_ascii_lowercase();
+    let mut ranges = Vec::new();
+
+    for term in terms
+    {
+        let term = term.to_ascii_lowercase();
+        if term.len() == 0 {continue;}
+
+        if query::is_glob(&term)
+        {
+            let mut word_start = None;
+            for (i, c) in lower.char_indices().chain(std::iter::once((lower.len(), ' ')))
+            {
+                if c.is_alphanumeric() || c == '_' {word_start = word_start.or(Some(i)); continue;}
+
+                let Some(start) = word_start.take() else { continue };
+                if query::glob_match(&term, &lower[start..i]) {ranges.push((start, i));}
+            }
+        }
+        else
+        {
+            ranges.extend(lower.match_indices(&term).map(|(i, x)| (i, i + x.len())));
+        }
+    }
+
+    ranges.sort();
+    return ranges;
+}
+
+fn display_notes(ui: &mut egui::Ui, notes: &mut String, active: &mut Option<Textbox>, terms: &Vec<String>) -> Option<Action>
 {
     let re = Regex::new(r"[^a-zA-Z\d_\ \\\//\-\|.?'!@#$%^&*():]").unwrap();
     *notes = re.replace_all(notes, "").to_string();
@@ -47,7 +84,27 @@ fn display_notes(ui: &mut egui::Ui, notes: &mut String, active: &mut Option<Text
             .background_color(egui::Color32::from_black_alpha(100))
             .size(10.0)));
 
-    let resp = ui.add(egui::TextEdit::multiline(notes).hint_text("add item"));
+    let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+        let format = egui::TextFormat::simple(egui::TextStyle::Body.resolve(ui.style()), ui.visuals().text_color());
+        let mut found = format.clone();
+        found.background = egui::Color32::from_rgb(120, 100, 20);
+
+        let mut job = egui::text::LayoutJob::default();
+        let mut pos = 0;
+        for (start, end) in note_matches(text, terms)
+        {
+            if end <= pos {continue;}
+            let start = start.max(pos);
+            job.append(&text[pos..start], 0.0, format.clone());
+            job.append(&text[start..end], 0.0, found.clone());
+            pos = end;
+        }
+        job.append(&text[pos..], 0.0, format);
+        job.wrap.max_width = wrap_width;
+        ui.fonts(|f| f.layout_job(job))
+    };
+
+    let resp = ui.add(egui::TextEdit::multiline(notes).hint_text("add item").layouter(&mut layouter));
     
     if active.is_some() && *active.as_mut().unwrap() == Textbox::Notes {resp.request_focus();}
     if resp.gained_focus() {*active = Some(Textbox::Notes);}
@@ -57,20 +114,49 @@ fn display_notes(ui: &mut egui::Ui, notes: &mut String, active: &mut Option<Text
     else {return None;}
 }
 
-fn display_vector(ui: &mut egui::Ui, textbox: &mut String, data_shared: &mut Shared, boxtype: Textbox) -> Option<Action>
+fn display_vector(ui: &mut egui::Ui, textbox: &mut String, img_data: &Data, data_shared: &mut Shared, boxtype: Textbox) -> Option<Action>
 {
     let mut result = None;
 
-    let vector = match boxtype
+    let mut vector = match boxtype
     {
-        Textbox::Artist =>  &data_shared.get_selected_tags()[0],
-        Textbox::Link => &data_shared.get_selected_tags()[1],
-        Textbox::Tag => &data_shared.get_selected_tags()[2],
-        _  => &data_shared.get_selected_tags()[0], // should never happen.
+        Textbox::Artist =>  data_shared.get_selected_tags()[0].clone(),
+        Textbox::Link => data_shared.get_selected_tags()[1].clone(),
+        Textbox::Tag => data_shared.get_selected_tags()[2].clone(),
+        _  => data_shared.get_selected_tags()[0].clone(), // should never happen.
     };
 
-    for (item, count) in vector
+    // namespaced tags go below the plain ones, grouped per namespace
+    let config = &img_data.config;
+    if boxtype == Textbox::Tag {vector.sort_by_cached_key(|x| config.split_namespace(&x.0).map(|x| x.0.to_string()));}
+    let mut group = None;
+
+    for (item, count) in &vector
     {
+        let namespace = if boxtype == Textbox::Tag {config.split_namespace(item)} else {None};
+        let (text, color) = match namespace
+        {
+            Some((name, value)) =>
+            {
+                let [r, g, b] = config.namespaces[name];
+                (value, Some(egui::Color32::from_rgb(r, g, b)))
+            },
+            None => (item.as_str(), None),
+        };
+
+        if namespace.is_some() && group != namespace.map(|x| x.0)
+        {
+            group = namespace.map(|x| x.0);
+            ui.add(egui::Label::new(RichText::new(group.unwrap()).color(color.unwrap()).size(10.0)));
+        }
+
+        let label = if *count == 1 {RichText::new(text)} else {RichText::new(format!("({count}): {text}"))};
+        let label = match color
+        {
+            Some(x) => label.color(x),
+            None => label,
+        };
+
         let resp_del = match boxtype
         {
             Textbox::Link => 
@@ -81,11 +167,12 @@ fn display_vector(ui: &mut egui::Ui, textbox: &mut String, data_shared: &mut Sha
             _ => 
             {
                 ui.horizontal(|ui| {
-                    if add_button(ui, "+") {result = Some(Action::SearchAdd(item.clone()));}
-                    if add_button(ui, "-") {result = Some(Action::SearchDel(item.clone()));}
+                    // searching the field keeps a tag from also finding an artist with the same name
+                    let term = format!("{}{}", if boxtype == Textbox::Artist {"artist:"} else {"tag:"}, query::quote_term(item));
+                    if add_button(ui, "+") {result = Some(Action::SearchAdd(term.clone()));}
+                    if add_button(ui, "-") {result = Some(Action::SearchDel(term));}
 
-                    if *count == 1 { ui.add(egui::Label::new(format!("{item}")).sense(egui::Sense::click()))}
-                    else { ui.add(egui::Label::new(format!("({count}): {item}")).sense(egui::Sense::click()))}
+                    ui.add(egui::Label::new(label).sense(egui::Sense::click()))
                 }).inner
             },
         };
@@ -109,10 +196,12 @@ fn display_vector(ui: &mut egui::Ui, textbox: &mut String, data_shared: &mut Sha
             egui::TextEdit::singleline(textbox).hint_text("add item")
             .frame(false)
         );
+    autocomplete(ui, &resp_add, textbox, boxtype.clone(), img_data, data_shared);
 
     let re = if boxtype == Textbox::Link {Regex::new(r"[^a-zA-Z\d_//.?():]").unwrap()}
              else {Regex::new(r"[^a-zA-Z\d_():]").unwrap()};
-    *textbox = re.replace_all(textbox, "").to_string();
+    // a picked suggestion is an existing name, keep it as it is so it doesn't turn into a near-duplicate
+    if *textbox != data_shared.completion.dismissed {*textbox = re.replace_all(textbox, "").to_string();}
 
     if resp_add.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && textbox.len() > 0 
     {
@@ -133,12 +222,37 @@ fn display_vector(ui: &mut egui::Ui, textbox: &mut String, data_shared: &mut Sha
     return result;
 }
 
+fn display_write_status(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &Shared) -> ()
+{
+    let failed: Vec<Index> = data_shared.get_selected().iter()
+        .filter(|i| img_data.folders[i.folder].images[i.image].write_error.is_some())
+        .cloned().collect();
+    let pending = data_shared.get_selected().iter()
+        .any(|i| img_data.folders[i.folder].images[i.image].pending_writes > 0);
+
+    if failed.len() > 0
+    {
+        let error = img_data.folders[failed[0].folder].images[failed[0].image].write_error.clone().unwrap();
+        ui.add(egui::Label::new(RichText::new(format!("saving failed: {}", error)).color(egui::Color32::LIGHT_RED)));
+        if ui.button("retry").clicked()
+        {
+            for index in &failed {img_data.retry_writes(index);}
+        }
+    }
+    else if pending
+    {
+        ui.add(egui::Label::new(RichText::new("saving...").color(egui::Color32::ORANGE)));
+    }
+}
+
 //////////////////////////////
 
 fn info_main(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared, boxes: &mut WndwRight) -> Option<Action>
 {
     let mut tag_action = None;
     let single_image = data_shared.get_selected().len() == 1;
+    display_write_status(ui, img_data, data_shared);
+
     let img = &mut img_data.folders[data_shared.get_selected()[0].folder].images[data_shared.get_selected()[0].image];
 
     ui.add(egui::Label::new(RichText::new("filepath")
@@ -147,6 +261,7 @@ fn info_main(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared, b
             
     if single_image {ui.add(egui::Label::new(&img.file));}
     else {ui.add(egui::Label::new("multiple images"));}
+
     ui.add(egui::Separator::default());
 
 
@@ -166,7 +281,7 @@ fn info_main(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared, b
         .background_color(egui::Color32::from_black_alpha(100))
         .size(10.0)));
     
-    let artist = display_vector(ui, &mut boxes.artist, data_shared, Textbox::Artist);
+    let artist = display_vector(ui, &mut boxes.artist, img_data, data_shared, Textbox::Artist);
     if !artist.is_none() && tag_action.is_none() {tag_action = artist;}
 
     ui.add(egui::Separator::default());
@@ -177,7 +292,7 @@ fn info_main(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared, b
             .background_color(egui::Color32::from_black_alpha(100))
             .size(10.0)));
 
-    let link = display_vector(ui, &mut boxes.link, data_shared, Textbox::Link);
+    let link = display_vector(ui, &mut boxes.link, img_data, data_shared, Textbox::Link);
     if !link.is_none() && tag_action.is_none() {tag_action = link;}
 
     ui.add(egui::Separator::default());
@@ -189,7 +304,7 @@ fn info_main(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared, b
             .background_color(egui::Color32::from_black_alpha(100))
             .size(10.0)));
     
-    let tag = display_vector(ui, &mut boxes.tag, data_shared, Textbox::Tag);
+    let tag = display_vector(ui, &mut boxes.tag, img_data, data_shared, Textbox::Tag);
     if !tag.is_none() && tag_action.is_none() {tag_action = tag;}
 
     /////////////////////////////////////////
@@ -198,13 +313,15 @@ fn info_main(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared, b
     ui.add(egui::Separator::default());
     ui.add(egui::Separator::default());
     
-    let notes = display_notes(ui, &mut img.notes, &mut data_shared.active_input);
+    let img = &mut img_data.folders[data_shared.get_selected()[0].folder].images[data_shared.get_selected()[0].image];
+    let terms = data_shared.notes_terms();
+    let notes = display_notes(ui, &mut img.notes, &mut data_shared.active_input, &terms);
     if !notes.is_none() && tag_action.is_none() {tag_action = notes;}
             
     return tag_action
 }
 
-pub fn wndw_right(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shared, boxes: &mut WndwRight) -> ()
+pub fn wndw_right(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shared, boxes: &mut WndwRight, history: &mut History) -> ()
 {
 
     egui::SidePanel::right("right_panel")
@@ -222,17 +339,11 @@ pub fn wndw_right(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Sha
                         
                         if action.is_none() {return;}
 
-                        match action.as_ref().unwrap()
+                        match action.unwrap()
                         {
-                            Action::ArtistAdd(x) => for i in data_shared.get_selected() {img_data.add_artist(i, &x);},
-                            Action::ArtistDel(x) => for i in data_shared.get_selected() {img_data.del_artist(i, &x);},
-                            Action::LinkAdd(x) => for i in data_shared.get_selected() {img_data.add_link(i, &x);},
-                            Action::LinkDel(x) => for i in data_shared.get_selected() {img_data.del_link(i, &x);},
-                            Action::TagAdd(x) => for i in data_shared.get_selected() {img_data.add_tag(i, &x);},
-                            Action::TagDel(x) => for i in data_shared.get_selected() {img_data.del_tag(i, &x);},
-                            Action::SearchAdd(x) => data_shared.add_to_search(img_data, x),
-                            Action::SearchDel(x) => data_shared.rem_from_search(img_data, x),
-                            Action::SetNotes => img_data.set_notes(&data_shared.main_img),
+                            Action::SearchAdd(x) => data_shared.add_to_search(img_data, &x),
+                            Action::SearchDel(x) => data_shared.rem_from_search(img_data, &x),
+                            x => history.apply(img_data, data_shared, x),
                         };
 
                         data_shared.update_tags(img_data);
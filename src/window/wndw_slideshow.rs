@@ -0,0 +1,225 @@
+use std::time::{Duration, Instant};
+use eframe::egui;
+use egui::widget_text::RichText;
+
+use crate::data::Data;
+use crate::shared::{Shared, Gallery};
+use crate::window::window::show_image;
+
+/////////////////////////
+
+// how many images to show and for how long, None repeats until stopped
+struct Stage
+{
+    count: Option<usize>,
+    seconds: u64,
+}
+
+pub struct Slideshow
+{
+    pub plan_text: String,
+    pub plan_error: Option<String>,
+    plan: Vec<Stage>,
+    running: bool,
+    paused: bool,
+    stage: usize,
+    shown: usize,
+    started: Instant,
+    elapsed: Duration,
+}
+
+/////////////////////////
+
+// "90" or "30s" or "2m" or "1m30s" to seconds
+fn parse_duration(text: &str) -> Option<u64>
+{
+    let mut seconds = 0;
+    let mut number = String::new();
+
+    for c in text.trim().chars()
+    {
+        match c
+        {
+            '0'..='9' => number.push(c),
+            'm' => {seconds += number.parse::<u64>().ok()? * 60; number.clear();},
+            's' => {seconds += number.parse::<u64>().ok()?; number.clear();},
+            _ => return None,
+        }
+    }
+
+    if number.len() > 0 {seconds += number.parse::<u64>().ok()?;}
+    if seconds == 0 {return None;}
+    return Some(seconds);
+}
+
+// "10x30s, 5x2m" to stages, a stage without a count runs until stopped
+fn parse_plan(text: &str) -> Result<Vec<Stage>, String>
+{
+    let mut plan = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|x| x.len() > 0)
+    {
+        let (count, time) = match part.split_once('x')
+        {
+            Some((count, time)) => match count.trim().parse::<usize>()
+            {
+                Ok(x) if x > 0 => (Some(x), time),
+                _ => return Err(format!("'{}' needs a count, like 10x30s", part)),
+            },
+            None => (None, part),
+        };
+
+        let Some(seconds) = parse_duration(time) else { return Err(format!("'{}' is not a time, like 30s or 2m", time.trim())) };
+        plan.push(Stage{count: count, seconds: seconds});
+    }
+
+    if plan.len() == 0 {return Err("the plan is empty".to_string());}
+    return Ok(plan);
+}
+
+fn format_time(seconds: u64) -> String
+{
+    return format!("{}:{:02}", seconds / 60, seconds % 60);
+}
+
+impl Slideshow
+{
+    pub fn new() -> Slideshow
+    {
+        Slideshow{
+            plan_text: "10x30s, 5x2m".to_string(),
+            plan_error: None,
+            plan: Vec::new(),
+            running: false,
+            paused: false,
+            stage: 0,
+            shown: 0,
+            started: Instant::now(),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn start(&mut self, img_data: &mut Data, data_shared: &mut Shared, plan: &str) -> ()
+    {
+        match parse_plan(plan)
+        {
+            Ok(x) => {self.plan = x; self.plan_error = None;},
+            Err(x) => {self.plan_error = Some(x); return;},
+        }
+        if data_shared.get_result_size() == 0 {self.plan_error = Some("no images to show".to_string()); return;}
+
+        self.running = true;
+        self.paused = false;
+        self.stage = 0;
+        self.shown = 0;
+        data_shared.gallery_type = Gallery::LeftBar;
+        self.next(img_data, data_shared);
+    }
+
+    pub fn stop(&mut self) -> ()
+    {
+        self.running = false;
+    }
+
+    fn toggle_pause(&mut self) -> ()
+    {
+        if self.paused {self.started = Instant::now();}
+        else {self.elapsed += self.started.elapsed();}
+        self.paused = !self.paused;
+    }
+
+    fn time_left(&self) -> u64
+    {
+        let elapsed = if self.paused {self.elapsed} else {self.elapsed + self.started.elapsed()};
+        return self.plan[self.stage].seconds.saturating_sub(elapsed.as_secs());
+    }
+
+    // moves on to the next image, and to the next stage once this one has shown enough
+    fn next(&mut self, img_data: &mut Data, data_shared: &mut Shared) -> ()
+    {
+        if self.shown > 0 && self.plan[self.stage].count.is_some_and(|x| self.shown >= x)
+        {
+            self.stage += 1;
+            self.shown = 0;
+            if self.stage >= self.plan.len() {self.running = false; return;}
+        }
+
+        // the first image of a session is the one already on screen
+        if self.stage > 0 || self.shown > 0
+        {
+            let Some(x) = data_shared.next_image() else { self.running = false; return };
+            show_image(img_data, data_shared, x);
+        }
+
+        self.shown += 1;
+        self.started = Instant::now();
+        self.elapsed = Duration::ZERO;
+    }
+
+    /////////////////////////
+
+    pub fn menu(&mut self, ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared) -> ()
+    {
+        for (text, plan) in [("30 seconds", "30s"), ("1 minute", "1m"), ("2 minutes", "2m"), ("5 minutes", "5m")]
+        {
+            if ui.button(text).clicked() {self.start(img_data, data_shared, plan); ui.close_menu();}
+        }
+
+        ui.separator();
+        ui.label("custom time or session plan:");
+        ui.add(egui::TextEdit::singleline(&mut self.plan_text).hint_text("10x30s, 5x2m"))
+          .on_hover_text("a time like 45s or 1m30s, or stages like 10x30s, 5x2m");
+
+        if ui.button("start").clicked()
+        {
+            let plan = self.plan_text.clone();
+            self.start(img_data, data_shared, &plan);
+            if self.running {ui.close_menu();}
+        }
+        if let Some(error) = &self.plan_error
+        {
+            ui.add(egui::Label::new(RichText::new(error).color(egui::Color32::LIGHT_RED).size(10.0)));
+        }
+    }
+
+    // countdown and controls on top of the image, and advancing when time is up
+    pub fn update(&mut self, ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shared) -> ()
+    {
+        if !self.running {return;}
+
+        // going back to the gallery ends the session
+        if data_shared.get_result_size() == 0 || data_shared.gallery_type != Gallery::LeftBar
+        {
+            self.running = false;
+            return;
+        }
+
+        if !self.paused && self.time_left() == 0 {self.next(img_data, data_shared);}
+        if !self.running {return;}
+
+        let stage = &self.plan[self.stage];
+        let progress = match stage.count
+        {
+            Some(x) => format!("{}/{}", self.shown, x),
+            None => format!("{}", self.shown),
+        };
+        let stages = if self.plan.len() > 1 {format!("stage {}/{}, ", self.stage + 1, self.plan.len())} else {String::new()};
+
+        egui::Area::new(egui::Id::new("slideshow"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -20.0))
+            .show(ui, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Label::new(RichText::new(format_time(self.time_left())).size(24.0).monospace()));
+                        ui.label(format!("{}image {}", stages, progress));
+
+                        if ui.button(if self.paused {"resume"} else {"pause"}).clicked() {self.toggle_pause();}
+                        if ui.button("skip").clicked() {self.next(img_data, data_shared);}
+                        if ui.button("stop").clicked() {self.stop();}
+                    });
+                });
+            });
+
+        if !self.paused {ui.request_repaint_after(Duration::from_millis(250));}
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use eframe::egui;
+use regex::Regex;
+use egui::widget_text::RichText;
+
+use crate::data::Data;
+use crate::shared::Shared;
+use crate::window::history::History;
+
+/////////////////////////
+
+#[derive(Default)]
+pub struct WndwTags
+{
+    pub filter: String,
+    pub name: String,
+    pub selected: HashSet<String>,
+    pub confirm_delete: bool,
+    pub message: Option<String>,
+    pub rule_tag: String,
+    pub rule_target: String,
+    pub namespace: String,
+}
+
+/////////////////////////
+
+fn apply(img_data: &mut Data, data_shared: &mut Shared, state: &mut WndwTags, history: &mut History,
+         replacement: Option<String>) -> ()
+{
+    let tags: Vec<String> = state.selected.drain().collect();
+    let count = history.replace_tags(img_data, &tags, replacement.as_ref());
+
+    state.message = match &replacement
+    {
+        Some(x) => Some(format!("changed {} to '{}' on {} images", tags.join(", "), x, count)),
+        None => Some(format!("removed {} from {} images", tags.join(", "), count)),
+    };
+    if let Some(x) = replacement {state.selected.insert(x);}
+
+    state.name.clear();
+    state.confirm_delete = false;
+    data_shared.update_search(img_data);
+    data_shared.update_tags(img_data);
+}
+
+fn tag_list(ui: &mut egui::Ui, img_data: &Data, state: &mut WndwTags) -> ()
+{
+    let filter = state.filter.to_lowercase();
+
+    egui::ScrollArea::vertical().max_height(250.0).auto_shrink([false, true]).show(ui, |ui| {
+        for (tag, count) in img_data.tag_counts()
+        {
+            if !tag.contains(&filter) {continue;}
+
+            let mut checked = state.selected.contains(&tag);
+            if ui.checkbox(&mut checked, format!("({count}): {tag}")).changed()
+            {
+                if checked {state.selected.insert(tag.clone());}
+                else {state.selected.remove(&tag);}
+                state.confirm_delete = false;
+            }
+        }
+    });
+}
+
+// aliases make a tag stand in for another, implications make searching a tag also find the tags implying it
+fn tag_rules(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared, state: &mut WndwTags) -> ()
+{
+    let mut changed = false;
+    let config = &mut img_data.config;
+
+    let mut aliases: Vec<(String, String)> = config.aliases.clone().into_iter().collect();
+    let mut implications: Vec<(String, String)> = config.implications.iter()
+        .flat_map(|(tag, implied)| implied.iter().map(|x| (tag.clone(), x.clone())))
+        .collect();
+    aliases.sort();
+    implications.sort();
+
+    for (alias, tag) in aliases
+    {
+        ui.horizontal(|ui| {
+            if ui.small_button("x").clicked() {config.set_alias(&alias, None); changed = true;}
+            ui.label(format!("{alias} is an alias of {tag}"));
+        });
+    }
+    for (tag, implied) in implications
+    {
+        ui.horizontal(|ui| {
+            if ui.small_button("x").clicked() {config.set_implication(&tag, &implied, false); changed = true;}
+            ui.label(format!("{tag} implies {implied}"));
+        });
+    }
+
+    let re = Regex::new(r"[^a-zA-Z\d_():]").unwrap();
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut state.rule_tag).hint_text("tag").desired_width(100.0));
+        ui.add(egui::TextEdit::singleline(&mut state.rule_target).hint_text("other tag").desired_width(100.0));
+    });
+    state.rule_tag = re.replace_all(&state.rule_tag, "").to_string().to_lowercase();
+    state.rule_target = re.replace_all(&state.rule_target, "").to_string().to_lowercase();
+
+    let valid = state.rule_tag.len() > 0 && state.rule_target.len() > 0 && state.rule_tag != state.rule_target;
+    ui.horizontal(|ui| {
+        if ui.add_enabled(valid, egui::Button::new("is an alias of")).clicked()
+        {
+            config.set_alias(&state.rule_tag, Some(&state.rule_target));
+            changed = true;
+        }
+        if ui.add_enabled(valid, egui::Button::new("implies")).clicked()
+        {
+            config.set_implication(&state.rule_tag, &state.rule_target, true);
+            changed = true;
+        }
+    });
+
+    if ui.checkbox(&mut config.write_implied, "add implied tags when tagging").changed() {config.save();}
+
+    if changed
+    {
+        state.rule_tag.clear();
+        state.rule_target.clear();
+        data_shared.update_search(img_data);
+    }
+}
+
+fn namespaces(ui: &mut egui::Ui, img_data: &mut Data, state: &mut WndwTags) -> ()
+{
+    let config = &mut img_data.config;
+    let mut names: Vec<String> = config.namespaces.keys().cloned().collect();
+    names.sort();
+
+    for name in names
+    {
+        ui.horizontal(|ui| {
+            if ui.small_button("x").clicked() {config.set_namespace(&name, None); return;}
+
+            let mut color = config.namespaces[&name];
+            if ui.color_edit_button_srgb(&mut color).changed() {config.set_namespace(&name, Some(color));}
+            ui.label(format!("{name}:*"));
+        });
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut state.namespace).hint_text("namespace").desired_width(100.0));
+        let re = Regex::new(r"[^a-zA-Z\d_()]").unwrap();
+        state.namespace = re.replace_all(&state.namespace, "").to_string().to_lowercase();
+
+        if ui.add_enabled(state.namespace.len() > 0, egui::Button::new("add")).clicked()
+        {
+            config.set_namespace(&state.namespace, Some([255, 255, 255]));
+            state.namespace.clear();
+        }
+    });
+}
+
+pub fn wndw_tags(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shared, state: &mut WndwTags,
+                 history: &mut History) -> ()
+{
+    let mut open = data_shared.show_popup_tags;
+
+    egui::Window::new("Manage tags").open(&mut open).default_width(250.0).show(ui, |ui| {
+        ui.add(egui::TextEdit::singleline(&mut state.filter).hint_text("filter tags"));
+        ui.add(egui::Separator::default());
+
+        tag_list(ui, img_data, state);
+        ui.add(egui::Separator::default());
+
+        // forget tags that were removed by something else in the meantime
+        state.selected.retain(|x| img_data.taglist.contains_key(x));
+        let selected = state.selected.len();
+
+        ui.add(egui::TextEdit::singleline(&mut state.name).hint_text("new name"));
+        let re = Regex::new(r"[^a-zA-Z\d_():]").unwrap();
+        state.name = re.replace_all(&state.name, "").to_string().to_lowercase();
+
+        ui.horizontal(|ui| {
+            let text = if selected > 1 {"merge into"} else {"rename"};
+            if ui.add_enabled(selected > 0 && state.name.len() > 0, egui::Button::new(text)).clicked()
+            {
+                let name = state.name.clone();
+                apply(img_data, data_shared, state, history, Some(name));
+            }
+
+            let text = if state.confirm_delete {"delete from all images?"} else {"delete"};
+            if ui.add_enabled(selected > 0, egui::Button::new(text)).clicked()
+            {
+                if state.confirm_delete {apply(img_data, data_shared, state, history, None);}
+                else {state.confirm_delete = true;}
+            }
+        });
+
+        if let Some(message) = &state.message
+        {
+            ui.add(egui::Label::new(RichText::new(message).size(10.0)));
+        }
+
+        ui.add(egui::Separator::default());
+        egui::CollapsingHeader::new("aliases and implications").show(ui, |ui| {
+            tag_rules(ui, img_data, data_shared, state);
+        });
+        egui::CollapsingHeader::new("namespaces").show(ui, |ui| {
+            namespaces(ui, img_data, state);
+        });
+    });
+
+    data_shared.show_popup_tags = open;
+}
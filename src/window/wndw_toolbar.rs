@@ -2,8 +2,12 @@ use eframe::egui;
 use egui::menu;
 
 use crate::data::Data;
-use crate::shared::{Shared, Gallery};
-use crate::data::image::Index;
+use crate::data::config::Sidecar;
+use crate::shared::{Shared, Gallery, Sort};
+use crate::data::image::{Index, Tone};
+use crate::window::wndw_slideshow::Slideshow;
+use crate::window::wndw_popout::Popout;
+use crate::window::window::toggle_fullscreen;
 
 fn open_folder() -> Vec<String>
 {
@@ -80,7 +84,7 @@ fn show_help(ui: &egui::Ui, data_shared: &mut Shared)
 
     egui::Window::new("Help").title_bar(true).open(&mut data_shared.show_popup_help).fixed_size(popup_size)
     .default_rect(egui::Rect{min: pos_min, max: pos_max}).show(ui.ctx(), |ui| {
-        ui.horizontal(|ui| {
+        egui::ScrollArea::vertical().show(ui, |ui| {
             
             ui.vertical(|ui| {
                 ui.label("opening and closing files:");
@@ -92,7 +96,18 @@ fn show_help(ui: &egui::Ui, data_shared: &mut Shared)
             
                 ui.add_space(12.0);
                 ui.label("tagging images:");
-                ui.label("images can have multiple tags to allow for easy searching, having multiple images selected will allow you to change tags on all selected.");            
+                ui.label("images can have multiple tags to allow for easy searching, having multiple images selected will allow you to change tags on all selected. ctrl+z undoes the last change, ctrl+y redoes it. the tags menu lists every tag in the opened folders and lets you rename, merge or delete them everywhere at once. browse shows every tag and artist: click one to search for it, right-click to exclude it.");
+
+                ui.add_space(12.0);
+                ui.label("searching:");
+                ui.label("terms separated by spaces must all match, '|' matches either side, '-' excludes a term and brackets group terms, e.g. '(hand | foot) -sketch'. '*' and '?' act as wildcards, so 'hand*' also finds 'hands', and 'pose:*' finds every tag in the pose namespace. a plain term matches both tags and artists, use tag:name, artist:name, source:domain or notes:\"some text\" to search a specific field. notes searches look for the text anywhere in the notes, with wildcards they match single words (notes:sketch*), and the matching part of the notes gets highlighted.");
+                ui.label("images can also be filtered on their properties: width:>2000, height:<=1080, orientation:landscape, ext:gif, animated:yes, filesize:<2mb and modified:2025-* or modified:>2025-01-01.");
+                ui.label("while typing a tag, artist or search term, matching tags are suggested: pick one with the up and down keys and press enter, or click it. with the search bar selected, the up and down keys otherwise step through earlier searches. while typing in any text field the arrow keys stay in that field, press escape to use them for browsing images again. searches you use often can be saved and rerun from the searches menu, which also shows how many images each one finds.");
+
+                ui.add_space(12.0);
+                ui.label("practice:");
+                ui.label("r jumps to a random image from the results, and shuffle playback in the sort menu makes the arrow keys go through them in a random order. no image comes up twice before all of them have been shown. the slideshow menu starts timed sessions: a single time per image, or a plan like 10x30s, 5x2m for ten images of 30 seconds followed by five of 2 minutes. the countdown at the bottom can pause, skip or stop the session.");
+                ui.label("the view menu changes how the image is shown without touching the file: h flips it, [ and ] rotate it, g shows it in grayscale and p steps through value studies of 2 to 5 values. f11 shows just the image across the whole screen, the arrow keys still change images and f11 or escape go back. pop out current image in the view menu pins the image in a small window that stays on top of other programs, right-click it to change its opacity or let clicks pass through to what is behind it. double-clicking it fits the image again.");
             });
         });
      });
@@ -143,10 +158,92 @@ fn show_about(ui: &egui::Ui, data_shared: &mut Shared)
      });
 }
 
+fn sort_menu(ui: &mut egui::Ui, img_data: &Data, data_shared: &mut Shared) -> ()
+{
+    let (mut sort, mut descending) = (data_shared.sort, data_shared.sort_descending);
+    let modes = [(Sort::Name, "file name"), (Sort::Modified, "date modified"), (Sort::FileSize, "file size"),
+                 (Sort::Pixels, "pixel count"), (Sort::Aspect, "aspect ratio"), (Sort::TagCount, "tag count"),
+                 (Sort::Shuffle, "shuffle")];
+
+    let mut clicked = false;
+    for (mode, text) in modes
+    {
+        clicked |= ui.radio_value(&mut sort, mode, text).clicked();
+    }
+    ui.separator();
+    clicked |= ui.checkbox(&mut descending, "descending").clicked();
+
+    if clicked {data_shared.set_sort(img_data, sort, descending);}
+
+    ui.separator();
+    ui.checkbox(&mut data_shared.shuffle_play, "shuffle playback")
+      .on_hover_text("the arrow keys go through the results in a random order, without repeats");
+}
+
+fn view_menu(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared, popout: &mut Popout) -> ()
+{
+    ui.checkbox(&mut data_shared.view.flip, "flip horizontally (h)");
+    ui.horizontal(|ui| {
+        if ui.button("rotate left ([)").clicked() {data_shared.rotate_view(img_data, 3);}
+        if ui.button("rotate right (])").clicked() {data_shared.rotate_view(img_data, 1);}
+    });
+
+    ui.separator();
+    let view = &mut data_shared.view;
+    ui.radio_value(&mut view.tone, Tone::Color, "color");
+    ui.radio_value(&mut view.tone, Tone::Grayscale, "grayscale (g)");
+    for levels in 2..=5
+    {
+        ui.radio_value(&mut view.tone, Tone::Values(levels), format!("{} values (p)", levels));
+    }
+
+    ui.separator();
+    if ui.button("reset view").clicked() {data_shared.reset_view(img_data);}
+    if ui.button("fullscreen (f11)").clicked() {toggle_fullscreen(ui.ctx(), img_data, data_shared); ui.close_menu();}
+
+    ui.separator();
+    popout.menu(ui, img_data, data_shared);
+}
+
+fn saved_searches(ui: &mut egui::Ui, img_data: &mut Data, data_shared: &mut Shared) -> ()
+{
+    let mut removed = None;
+    for (name, search) in &img_data.config.saved_searches
+    {
+        let count = match img_data.count_matches(search)
+        {
+            Some(x) => format!("{}", x),
+            None => "error".to_string(),
+        };
+
+        ui.horizontal(|ui| {
+            if ui.small_button("x").on_hover_text("remove").clicked() {removed = Some(name.clone());}
+            if ui.button(format!("{} ({})", name, count)).on_hover_text(search).clicked()
+            {
+                data_shared.search = search.clone();
+                data_shared.update_search(img_data);
+                ui.close_menu();
+            }
+        });
+    }
+    if let Some(name) = removed {img_data.config.remove_search(&name);}
+
+    if img_data.config.saved_searches.len() > 0 {ui.separator();}
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut data_shared.search_name).hint_text("name").desired_width(100.0));
+        let valid = data_shared.search_name.trim().len() > 0 && data_shared.search.trim().len() > 0;
+        if ui.add_enabled(valid, egui::Button::new("save current search")).clicked()
+        {
+            img_data.config.save_search(&data_shared.search_name.trim().to_string(), &data_shared.search.trim().to_string());
+            data_shared.search_name.clear();
+        }
+    });
+}
+
 ////////////////////////////
 
 
-pub fn wndw_toolbar(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shared ) -> ()
+pub fn wndw_toolbar(ui: &egui::Context, img_data: &mut Data, data_shared: &mut Shared, slideshow: &mut Slideshow, popout: &mut Popout) -> ()
 {
     egui::TopBottomPanel::top("my_panel").show(ui, |ui| {
         menu::bar(ui, |ui| {
@@ -165,12 +262,37 @@ pub fn wndw_toolbar(ui: &egui::Context, img_data: &mut Data, data_shared: &mut S
                     if paths.len() == 0 {return;}
                     update_data(img_data, data_shared, paths);
                 }
+
+                ui.separator();
+                ui.menu_button("Store tags in", |ui| {
+                    let before = img_data.config.sidecar;
+                    ui.radio_value(&mut img_data.config.sidecar, Sidecar::Off, "image files");
+                    ui.radio_value(&mut img_data.config.sidecar, Sidecar::Json, "json sidecar files");
+                    ui.radio_value(&mut img_data.config.sidecar, Sidecar::Xmp, "xmp sidecar files");
+                    if img_data.config.sidecar != before {img_data.config.save();}
+                });
             });
 
+            ui.menu_button("View", |ui| {view_menu(ui, img_data, data_shared, popout);});
+            ui.menu_button("Sort", |ui| {sort_menu(ui, img_data, data_shared);});
+            ui.menu_button("Slideshow", |ui| {slideshow.menu(ui, img_data, data_shared);});
+            ui.menu_button("Searches", |ui| {saved_searches(ui, img_data, data_shared);});
+            if ui.button("Tags").clicked() {data_shared.show_popup_tags = !data_shared.show_popup_tags;}
+            if ui.button("Browse").clicked() {data_shared.show_tag_browser = !data_shared.show_tag_browser;}
             if ui.button("About").clicked() {data_shared.show_popup_about = !data_shared.show_popup_about;}
             if ui.button("Help").clicked() {data_shared.show_popup_help = !data_shared.show_popup_help;}
             if data_shared.show_popup_about {show_about(ui, data_shared);}
             if data_shared.show_popup_help {show_help(ui, data_shared);}
+
+            let failed = img_data.get_failed_writes();
+            if failed.len() > 0
+            {
+                let text = egui::RichText::new(format!("{} images failed to save", failed.len())).color(egui::Color32::LIGHT_RED);
+                if ui.button(text).on_hover_text("retry saving").clicked()
+                {
+                    for index in &failed {img_data.retry_writes(index);}
+                }
+            }
         });
     });
 }
\ No newline at end of file